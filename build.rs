@@ -15,4 +15,7 @@ fn main() {
     config.include(vendor_path.join("libvterm/include").to_str().unwrap());
     config.include(vendor_path.join("libvterm/src").to_str().unwrap());
     config.compile("libvterm.a");
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=vendor");
 }
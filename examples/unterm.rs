@@ -122,10 +122,7 @@ fn dump_cell(vterm: &VTerm, cell: &ScreenCell, prev_cell: &ScreenCell, context:
         }
     }
 
-    std::io::stdout()
-        .write_all(&cell.chars)
-        .ok()
-        .expect("failed to write");
+    print!("{}", cell);
 }
 
 fn dump_eol(prev_cell: &ScreenCell, context: &Context) {
@@ -154,7 +151,7 @@ fn dump_row(row: usize, vt: &VTerm, context: &Context) {
     prev_cell.fg_rgb = fg_rgb;
     prev_cell.bg_rgb = bg_rgb;
 
-    let mut pos = Pos { x: row, y: 0 };
+    let mut pos = Pos { x: 0, y: row };
     while pos.x < context.cols_count as usize {
         let cell = vt.screen_get_cell(&pos);
 
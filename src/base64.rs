@@ -0,0 +1,77 @@
+//! Just enough base64 for OSC 52, which uses the standard alphabet with padding.
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() + 2) / 3 * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    out
+}
+
+/// Returns None if the input isn't valid base64. Padding is optional.
+pub fn decode(input: &[u8]) -> Option<Vec<u8>> {
+    let input = match input.iter().position(|&b| b == b'=') {
+        Some(pad) if input[pad..].iter().all(|&b| b == b'=') && input.len() % 4 == 0 => {
+            &input[..pad]
+        }
+        Some(_) => return None,
+        None => input,
+    };
+    if input.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    for chunk in input.chunks(4) {
+        let mut n: u32 = 0;
+        for (i, &b) in chunk.iter().enumerate() {
+            let value = ALPHABET.iter().position(|&a| a == b)? as u32;
+            n |= value << (18 - 6 * i);
+        }
+
+        let bytes = [(n >> 16) as u8, (n >> 8) as u8, n as u8];
+        out.extend_from_slice(&bytes[..chunk.len() - 1]);
+    }
+
+    Some(out)
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn base64_round_trips() {
+        for input in &["", "f", "fo", "foo", "foob", "fooba", "foobar"] {
+            let encoded = encode(input.as_bytes());
+            assert_eq!(decode(encoded.as_bytes()), Some(input.as_bytes().to_vec()));
+        }
+        assert_eq!(encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(encode(b"fo"), "Zm8=");
+    }
+
+    #[test]
+    fn base64_decode_rejects_invalid_input() {
+        assert_eq!(decode(b"Zm8"), Some(b"fo".to_vec()));
+        assert_eq!(decode(b"Z"), None);
+        assert_eq!(decode(b"Zm8=Zm8="), None);
+        assert_eq!(decode(b"Zm!="), None);
+    }
+}
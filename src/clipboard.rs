@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use super::*;
+
+/// The selections OSC 52 can name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Selection {
+    Clipboard,
+    Primary,
+    Secondary,
+    Select,
+    /// Cut buffers 0 to 7
+    CutBuffer(u8),
+}
+
+impl Selection {
+    fn from_byte(b: u8) -> Option<Selection> {
+        match b {
+            b'c' => Some(Selection::Clipboard),
+            b'p' => Some(Selection::Primary),
+            b'q' => Some(Selection::Secondary),
+            b's' => Some(Selection::Select),
+            b'0'..=b'7' => Some(Selection::CutBuffer(b - b'0')),
+            _ => None,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Selection::Clipboard => b'c',
+            Selection::Primary => b'p',
+            Selection::Secondary => b'q',
+            Selection::Select => b's',
+            Selection::CutBuffer(n) => b'0' + n,
+        }
+    }
+}
+
+/// Where OSC 52 reads and writes go. Data is passed on as decoded bytes, which programs usually
+/// but not always send as utf8.
+pub trait Clipboard {
+    fn set(&mut self, selection: Selection, data: Vec<u8>);
+
+    fn get(&mut self, selection: Selection) -> Option<Vec<u8>>;
+}
+
+/// A Clipboard that only keeps the data in memory. This is what a VTerm starts with.
+#[derive(Debug, Default)]
+pub struct MemoryClipboard {
+    selections: HashMap<Selection, Vec<u8>>,
+}
+
+impl MemoryClipboard {
+    pub fn new() -> MemoryClipboard {
+        Default::default()
+    }
+}
+
+impl Clipboard for MemoryClipboard {
+    fn set(&mut self, selection: Selection, data: Vec<u8>) {
+        self.selections.insert(selection, data);
+    }
+
+    fn get(&mut self, selection: Selection) -> Option<Vec<u8>> {
+        self.selections.get(&selection).cloned()
+    }
+}
+
+/// What programs are allowed to do with the clipboard. Reading is off by default, since it
+/// lets any program in the terminal see what the user copied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardPolicy {
+    pub allow_write: bool,
+    pub allow_read: bool,
+}
+
+impl ClipboardPolicy {
+    pub fn all() -> ClipboardPolicy {
+        ClipboardPolicy {
+            allow_write: true,
+            allow_read: true,
+        }
+    }
+
+    pub fn none() -> ClipboardPolicy {
+        ClipboardPolicy {
+            allow_write: false,
+            allow_read: false,
+        }
+    }
+}
+
+/// Handle the part of an OSC 52 after the `52;`, which is `selections;data`. Data is base64 to
+/// set the selections or `?` to ask for the first one, and the answer is written to `response`.
+pub(crate) fn handle_osc52(
+    clipboard: &mut dyn Clipboard,
+    policy: &ClipboardPolicy,
+    data: &[u8],
+    response: &mut Vec<u8>,
+) {
+    let split = match data.iter().position(|&b| b == b';') {
+        Some(split) => split,
+        None => return,
+    };
+    let (names, data) = (&data[..split], &data[split + 1..]);
+
+    // Like most terminals, though not xterm, no selection means the clipboard
+    let mut selections: Vec<Selection> = names
+        .iter()
+        .filter_map(|&b| Selection::from_byte(b))
+        .collect();
+    if selections.is_empty() {
+        selections.push(Selection::Clipboard);
+    }
+
+    if data == b"?" {
+        if !policy.allow_read {
+            return;
+        }
+
+        let selection = selections[0];
+        let contents = clipboard.get(selection).unwrap_or_default();
+        response.extend_from_slice(b"\x1b]52;");
+        response.push(selection.to_byte());
+        response.push(b';');
+        response.extend_from_slice(base64::encode(&contents).as_bytes());
+        response.extend_from_slice(b"\x1b\\");
+    } else if policy.allow_write {
+        // xterm clears the selection when the data isn't valid base64
+        let contents = base64::decode(data).unwrap_or_default();
+        for &selection in &selections {
+            clipboard.set(selection, contents.clone());
+        }
+    }
+}
+
+impl VTerm {
+    /// Replace the clipboard OSC 52 reads and writes go to
+    pub fn set_clipboard(&mut self, clipboard: Box<dyn Clipboard>) {
+        unsafe { self.callback_data.as_mut() }.clipboard = clipboard;
+    }
+
+    pub fn clipboard_mut(&mut self) -> &mut dyn Clipboard {
+        unsafe { self.callback_data.as_mut() }.clipboard.as_mut()
+    }
+
+    /// Set what OSC 52 may do. With `ClipboardPolicy::none()`, the default, OSC 52 isn't handled
+    /// at all and goes to the unrecognised handler like any other sequence. Otherwise requests
+    /// that aren't allowed are dropped.
+    pub fn set_clipboard_policy(&mut self, policy: ClipboardPolicy) {
+        unsafe { self.callback_data.as_mut() }.clipboard_policy = policy;
+    }
+
+    pub fn clipboard_policy(&self) -> &ClipboardPolicy {
+        &unsafe { self.callback_data.as_ref() }.clipboard_policy
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::super::*;
+    use super::*;
+
+    #[test]
+    fn osc52_sets_every_named_selection() {
+        let mut clipboard = MemoryClipboard::new();
+        let mut response = vec![];
+        handle_osc52(
+            &mut clipboard,
+            &ClipboardPolicy::all(),
+            b"cp;aGk=",
+            &mut response,
+        );
+
+        assert_eq!(clipboard.get(Selection::Clipboard), Some(b"hi".to_vec()));
+        assert_eq!(clipboard.get(Selection::Primary), Some(b"hi".to_vec()));
+        assert_eq!(clipboard.get(Selection::Select), None);
+        assert_eq!(response, vec![]);
+    }
+
+    #[test]
+    fn osc52_answers_queries_for_the_first_selection() {
+        let mut clipboard = MemoryClipboard::new();
+        clipboard.set(Selection::Primary, b"hi".to_vec());
+
+        let mut response = vec![];
+        handle_osc52(
+            &mut clipboard,
+            &ClipboardPolicy::all(),
+            b"pc;?",
+            &mut response,
+        );
+        assert_eq!(response, b"\x1b]52;p;aGk=\x1b\\".to_vec());
+
+        let mut response = vec![];
+        handle_osc52(
+            &mut clipboard,
+            &ClipboardPolicy::all(),
+            b";?",
+            &mut response,
+        );
+        assert_eq!(response, b"\x1b]52;c;\x1b\\".to_vec());
+    }
+
+    #[test]
+    fn osc52_follows_the_policy() {
+        let mut clipboard = MemoryClipboard::new();
+        clipboard.set(Selection::Clipboard, b"secret".to_vec());
+        let write_only = ClipboardPolicy {
+            allow_write: true,
+            allow_read: false,
+        };
+        let read_only = ClipboardPolicy {
+            allow_write: false,
+            allow_read: true,
+        };
+
+        let mut response = vec![];
+        handle_osc52(&mut clipboard, &write_only, b"c;?", &mut response);
+        handle_osc52(&mut clipboard, &read_only, b"c;aGk=", &mut response);
+
+        assert_eq!(response, vec![]);
+        assert_eq!(
+            clipboard.get(Selection::Clipboard),
+            Some(b"secret".to_vec())
+        );
+    }
+
+    #[test]
+    fn osc52_clears_on_invalid_base64() {
+        let mut clipboard = MemoryClipboard::new();
+        clipboard.set(Selection::Clipboard, b"hi".to_vec());
+        let mut response = vec![];
+        handle_osc52(
+            &mut clipboard,
+            &ClipboardPolicy::all(),
+            b"c;!",
+            &mut response,
+        );
+
+        assert_eq!(clipboard.get(Selection::Clipboard), Some(vec![]));
+    }
+}
@@ -0,0 +1,77 @@
+use std::error;
+use std::fmt;
+use std::string::FromUtf8Error;
+
+use super::*;
+
+/// Errors returned by this crate
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Error {
+    /// A position, or for rects their bottom right corner, is outside the screen. The screen can
+    /// shrink between a resize and the next query, so this can happen to correct code.
+    OutOfBounds { size: Size, pos: Pos },
+    /// The screen text wasn't valid utf8
+    InvalidUtf8(FromUtf8Error),
+    /// libvterm couldn't allocate memory
+    AllocationFailed,
+    /// A theme had a colour that couldn't be parsed on the given line, counting from 1
+    InvalidTheme { line: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::OutOfBounds { ref size, ref pos } => write!(
+                f,
+                "position out of bounds: size={}x{} pos={},{}",
+                size.width, size.height, pos.x, pos.y
+            ),
+            Error::InvalidUtf8(ref err) => write!(f, "invalid utf8: {}", err),
+            Error::AllocationFailed => write!(f, "libvterm allocation failed"),
+            Error::InvalidTheme { line } => write!(f, "invalid colour on theme line {}", line),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::InvalidUtf8(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<FromUtf8Error> for Error {
+    fn from(err: FromUtf8Error) -> Error {
+        Error::InvalidUtf8(err)
+    }
+}
+
+/// Check the position is on a screen of the given size
+pub(crate) fn check_pos(size: Size, pos: &Pos) -> Result<(), Error> {
+    if pos.x >= size.width || pos.y >= size.height {
+        return Err(Error::OutOfBounds {
+            size: size,
+            pos: pos.clone(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Check the rect fits on a screen of the given size
+pub(crate) fn check_rect(size: Size, rect: &Rect) -> Result<(), Error> {
+    let screen_rect = Rect::new(Pos::new(0, 0), size);
+    if !screen_rect.contains_rect(rect) {
+        return Err(Error::OutOfBounds {
+            size: size,
+            pos: Pos::new(
+                rect.right().saturating_sub(1).max(rect.left()),
+                rect.bottom().saturating_sub(1).max(rect.top()),
+            ),
+        });
+    }
+
+    Ok(())
+}
@@ -1,4 +1,7 @@
+use std::ffi::CStr;
+
 use crate::prelude::*;
+use crate::{ffi, int_to_bool, Color, PaletteLookup};
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct AltScreenEvent {
@@ -50,7 +53,8 @@ pub struct ReverseEvent {
 }
 #[derive(PartialEq, Debug, Clone)]
 pub struct SbPopLineEvent {
-    pub cells: Vec<ScreenCell>,
+    /// The width of the line libvterm asked for. A channel can't answer, so nothing is restored.
+    pub cols: usize,
 }
 #[derive(PartialEq, Debug, Clone)]
 pub struct SbPushLineEvent {
@@ -113,16 +117,153 @@ pub struct PenFontEvent {
 pub struct PenForegroundEvent {
     pub rgb: ColorRGB,
     pub palette: ColorPalette,
+    pub color: Color,
 }
 #[derive(PartialEq, Debug, Clone)]
 pub struct PenBackgroundEvent {
     pub rgb: ColorRGB,
     pub palette: ColorPalette,
+    pub color: Color,
 }
 #[derive(PartialEq, Debug, Clone)]
 pub struct LineInfoEvent {
-    // TODO: add line info stuff here
     pub row: i32,
+    pub new: LineInfo,
+    pub old: LineInfo,
+}
+
+/// A terminal property change, as given to `ScreenHandler::set_term_prop` and
+/// `StateHandler::set_term_prop`.
+#[derive(PartialEq, Debug, Clone)]
+pub enum TermProp {
+    AltScreen(bool),
+    CursorBlink(bool),
+    CursorShape(CursorShape),
+    CursorVisible(bool),
+    IconName(String),
+    Mouse(MouseMode),
+    Reverse(bool),
+    Title(String),
+}
+
+impl TermProp {
+    /// Decode the value libvterm passes for the given prop. Returns None for values we don't
+    /// know, like the cursor shape of 0 libvterm reports when restoring an unsaved cursor.
+    pub(crate) fn from_ffi(prop: ffi::VTermProp, val: *mut ffi::VTermValue) -> Option<TermProp> {
+        let prop = match prop {
+            ffi::VTermProp::VTermPropAltscreen => {
+                TermProp::AltScreen(unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)) })
+            }
+            ffi::VTermProp::VTermPropCursorBlink => {
+                TermProp::CursorBlink(unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)) })
+            }
+            ffi::VTermProp::VTermPropCursorShape => {
+                TermProp::CursorShape(CursorShape::try_from_i32(unsafe {
+                    ffi::vterm_value_get_number(val)
+                })?)
+            }
+            ffi::VTermProp::VTermPropCursorVisible => {
+                TermProp::CursorVisible(unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)) })
+            }
+            ffi::VTermProp::VTermPropIconName => TermProp::IconName(unsafe {
+                CStr::from_ptr(ffi::vterm_value_get_string(val))
+                    .to_string_lossy()
+                    .into_owned()
+            }),
+            ffi::VTermProp::VTermPropMouse => TermProp::Mouse(MouseMode::try_from_i32(unsafe {
+                ffi::vterm_value_get_number(val)
+            })?),
+            ffi::VTermProp::VTermPropReverse => {
+                TermProp::Reverse(unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)) })
+            }
+            ffi::VTermProp::VTermPropTitle => TermProp::Title(unsafe {
+                CStr::from_ptr(ffi::vterm_value_get_string(val))
+                    .to_string_lossy()
+                    .into_owned()
+            }),
+        };
+
+        Some(prop)
+    }
+}
+
+/// A pen attribute change, as given to `StateHandler::set_pen_attr`.
+#[derive(PartialEq, Debug, Clone)]
+pub enum PenAttr {
+    Background {
+        rgb: ColorRGB,
+        palette: ColorPalette,
+        color: Color,
+    },
+    Blink(bool),
+    Bold(bool),
+    Font(i32),
+    Foreground {
+        rgb: ColorRGB,
+        palette: ColorPalette,
+        color: Color,
+    },
+    Italic(bool),
+    Reverse(bool),
+    Strike(bool),
+    Underline(Underline),
+}
+
+impl PenAttr {
+    /// Decode the value libvterm passes for the given attr. The state and palette lookup are
+    /// needed to find the palette index of colors. Returns None for hyperlinks, which the crate
+    /// tracks itself.
+    pub(crate) fn from_ffi(
+        attr: ffi::VTermAttr,
+        val: *mut ffi::VTermValue,
+        state: &ffi::VTermState,
+        palette_lookup: &PaletteLookup,
+    ) -> Option<PenAttr> {
+        let attr = match attr {
+            ffi::VTermAttr::Background => {
+                let rgb: ColorRGB = unsafe { ffi::vterm_value_get_color(val).as_color_rgb() };
+                let color =
+                    Color::from_ffi(unsafe { ffi::vterm_state_get_pen_bg_index(state) }, &rgb);
+                PenAttr::Background {
+                    palette: color.palette_index(&rgb, state, palette_lookup),
+                    rgb: rgb,
+                    color: color,
+                }
+            }
+            ffi::VTermAttr::Blink => {
+                PenAttr::Blink(unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)) })
+            }
+            ffi::VTermAttr::Bold => {
+                PenAttr::Bold(unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)) })
+            }
+            ffi::VTermAttr::Font => PenAttr::Font(unsafe { ffi::vterm_value_get_number(val) }),
+            ffi::VTermAttr::Foreground => {
+                let rgb: ColorRGB = unsafe { ffi::vterm_value_get_color(val).as_color_rgb() };
+                let color =
+                    Color::from_ffi(unsafe { ffi::vterm_state_get_pen_fg_index(state) }, &rgb);
+                PenAttr::Foreground {
+                    palette: color.palette_index(&rgb, state, palette_lookup),
+                    rgb: rgb,
+                    color: color,
+                }
+            }
+            ffi::VTermAttr::Italic => {
+                PenAttr::Italic(unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)) })
+            }
+            ffi::VTermAttr::Reverse => {
+                PenAttr::Reverse(unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)) })
+            }
+            ffi::VTermAttr::Strike => {
+                PenAttr::Strike(unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)) })
+            }
+            ffi::VTermAttr::Underline => PenAttr::Underline(Underline::from_i32(unsafe {
+                ffi::vterm_value_get_number(val)
+            })),
+            ffi::VTermAttr::Uri => return None,
+        };
+
+        Some(attr)
+    }
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -134,11 +275,18 @@ pub enum CursorShape {
 
 impl CursorShape {
     pub fn from_i32(val: i32) -> CursorShape {
+        CursorShape::try_from_i32(val)
+            .unwrap_or_else(|| panic!("unknown cursor shape value: {}", val))
+    }
+
+    /// Like `from_i32` but returns None for unknown values. libvterm can report a shape of 0 when
+    /// a cursor is restored without having been saved first.
+    pub fn try_from_i32(val: i32) -> Option<CursorShape> {
         match val {
-            1 => CursorShape::Block,
-            2 => CursorShape::Underline,
-            3 => CursorShape::BarLeft,
-            _ => panic!("unknown cursor shape value: {}", val),
+            1 => Some(CursorShape::Block),
+            2 => Some(CursorShape::Underline),
+            3 => Some(CursorShape::BarLeft),
+            _ => None,
         }
     }
 }
@@ -153,12 +301,16 @@ pub enum MouseMode {
 
 impl MouseMode {
     pub fn from_i32(val: i32) -> MouseMode {
+        MouseMode::try_from_i32(val).unwrap_or_else(|| panic!("unknown mouse mode value: {}", val))
+    }
+
+    pub fn try_from_i32(val: i32) -> Option<MouseMode> {
         match val {
-            0 => MouseMode::None,
-            1 => MouseMode::Click,
-            2 => MouseMode::Drag,
-            3 => MouseMode::Move,
-            _ => panic!("unknown mouse mode value: {}", val),
+            0 => Some(MouseMode::None),
+            1 => Some(MouseMode::Click),
+            2 => Some(MouseMode::Drag),
+            3 => Some(MouseMode::Move),
+            _ => None,
         }
     }
 }
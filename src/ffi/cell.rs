@@ -1,8 +1,31 @@
-use super::VTermColor;
+use super::{VTermColor, VTERM_MAX_CHARS_PER_CELL};
 use libc::{c_char, c_int, c_uint, size_t};
 
 pub enum VTermScreenCell {}
 
+/// A VTermScreenCell without bitfields, as filled in by `vterm_screen_get_flat_cells`. Keep in
+/// step with the struct in rusty_shims.c.
+#[repr(C)]
+#[derive(Debug, Default, Clone)]
+pub struct VTermFlatCell {
+    pub chars: [u32; VTERM_MAX_CHARS_PER_CELL],
+    pub fg: VTermColor,
+    pub bg: VTermColor,
+    pub fg_index: c_int,
+    pub bg_index: c_int,
+    pub uri: c_int,
+    pub width: c_char,
+    pub bold: u8,
+    pub underline: u8,
+    pub italic: u8,
+    pub blink: u8,
+    pub reverse: u8,
+    pub strike: u8,
+    pub font: u8,
+    pub dwl: u8,
+    pub dhl: u8,
+}
+
 /// There should be rust methods here to pull stuff out of the cell, also a into_screen_cell method
 /// to cast to ScreenCell
 
@@ -40,6 +63,12 @@ extern "C" {
     pub fn vterm_cell_set_fg(cell: *mut VTermScreenCell, color: VTermColor);
     pub fn vterm_cell_get_bg(cell: *const VTermScreenCell) -> VTermColor;
     pub fn vterm_cell_set_bg(cell: *mut VTermScreenCell, color: VTermColor);
+    pub fn vterm_cell_get_uri(cell: *const VTermScreenCell) -> c_int;
+    pub fn vterm_cell_set_uri(cell: *mut VTermScreenCell, uri: c_int);
+    pub fn vterm_cell_get_fg_index(cell: *const VTermScreenCell) -> c_int;
+    pub fn vterm_cell_set_fg_index(cell: *mut VTermScreenCell, index: c_int);
+    pub fn vterm_cell_get_bg_index(cell: *const VTermScreenCell) -> c_int;
+    pub fn vterm_cell_set_bg_index(cell: *mut VTermScreenCell, index: c_int);
     pub fn vterm_cell_pointer_arithmetic(
         cell: *const VTermScreenCell,
         amount: c_int,
@@ -0,0 +1,10 @@
+use libc::c_uint;
+
+pub enum VTermLineInfo {}
+
+extern "C" {
+    // These are my rust ffi bitfield workarounds
+    pub fn vterm_line_info_get_doublewidth(line_info: *const VTermLineInfo) -> c_uint;
+    pub fn vterm_line_info_get_doubleheight(line_info: *const VTermLineInfo) -> c_uint;
+    pub fn vterm_line_info_get_continuation(line_info: *const VTermLineInfo) -> c_uint;
+}
@@ -1,5 +1,6 @@
 mod cell;
 mod glyph_info;
+mod line_info;
 mod parser;
 mod screen;
 mod state;
@@ -7,6 +8,7 @@ mod vterm;
 
 pub use self::cell::*;
 pub use self::glyph_info::*;
+pub use self::line_info::*;
 pub use self::parser::*;
 pub use self::screen::*;
 pub use self::state::*;
@@ -18,6 +20,10 @@ pub use crate::prelude::*;
 
 pub const VTERM_MAX_CHARS_PER_CELL: usize = 6;
 
+// What a pen or cell's colour index is when the colour isn't from the palette
+pub const VTERM_COLOR_INDEX_DEFAULT: c_int = -1;
+pub const VTERM_COLOR_INDEX_RGB: c_int = -2;
+
 #[derive(Debug)]
 #[repr(C)]
 pub enum VTermProp {
@@ -33,8 +39,6 @@ pub enum VTermProp {
 
 pub enum VTermValue {}
 
-pub enum VTermLineInfo {}
-
 #[repr(C)]
 #[derive(PartialEq, Debug)]
 pub struct VTermPos {
@@ -67,6 +71,14 @@ pub struct VTermColor {
 }
 
 impl VTermColor {
+    pub fn from_color_rgb(rgb: &ColorRGB) -> VTermColor {
+        VTermColor {
+            red: rgb.red,
+            green: rgb.green,
+            blue: rgb.blue,
+        }
+    }
+
     pub fn as_color_rgb(&self) -> ColorRGB {
         ColorRGB {
             red: self.red,
@@ -122,6 +134,7 @@ pub enum VTermAttr {
     Font,       // number: 10-19
     Foreground, // color:  30-39 90-97
     Background, // color:  40-49 100-107
+    Uri,        // number: OSC 8 hyperlink id
 }
 
 #[repr(C)]
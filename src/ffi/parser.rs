@@ -2,23 +2,47 @@ use libc::{c_char, c_int, c_long, c_uchar, c_void, size_t};
 
 use super::*;
 
+/// Set on a CSI argument that is followed by a sub-parameter, i.e. a `:` rather than a `;`
+pub const CSI_ARG_FLAG_MORE: c_long = 1 << 31;
+/// Written out rather than `!CSI_ARG_FLAG_MORE` because the C mask is an int, so it doesn't cover
+/// the high bits of a 64 bit long.
+pub const CSI_ARG_MASK: c_long = 0x7fff_ffff;
+/// An argument that was left empty
+pub const CSI_ARG_MISSING: c_long = 0x7fff_ffff;
+
 #[repr(C)]
+#[derive(Debug, Clone, Copy)]
 pub struct VTermParserCallbacks {
-    pub text: extern "C" fn(bytes: *const c_char, len: size_t, user: *mut c_void) -> c_int,
-    pub control: extern "C" fn(control: c_uchar, user: *mut c_void) -> c_int,
+    pub text: Option<extern "C" fn(*const c_char, size_t, *mut c_void) -> c_int>,
+    pub control: Option<extern "C" fn(c_uchar, *mut c_void) -> c_int>,
+    pub escape: Option<extern "C" fn(*const c_char, size_t, *mut c_void) -> c_int>,
+    pub csi: Option<
+        extern "C" fn(
+            *const c_char,
+            *const c_long,
+            c_int,
+            *const c_char,
+            c_char,
+            *mut c_void,
+        ) -> c_int,
+    >,
+    pub osc: Option<extern "C" fn(*const c_char, size_t, *mut c_void) -> c_int>,
+    pub dcs: Option<extern "C" fn(*const c_char, size_t, *mut c_void) -> c_int>,
+    pub resize: Option<extern "C" fn(c_int, c_int, *mut c_void) -> c_int>,
+}
 
-    pub escape: extern "C" fn(bytes: *const c_char, lne: size_t, user: *mut c_void) -> c_int,
-    pub csi: extern "C" fn(
-        leader: *const c_char,
-        args: *const c_long,
-        argcount: c_int,
-        intermed: *const c_char,
-        command: c_char,
-        user: *mut c_void,
-    ) -> c_int,
-    pub osc: extern "C" fn(command: *const c_char, cmdlen: size_t, user: *mut c_void) -> c_int,
-    pub dcs: extern "C" fn(command: *const c_char, cmdlen: size_t, user: *mut c_void) -> c_int,
-    pub resize: extern "C" fn(rows: c_int, cols: c_int, user: *mut c_void) -> c_int,
+impl Default for VTermParserCallbacks {
+    fn default() -> VTermParserCallbacks {
+        VTermParserCallbacks {
+            text: None,
+            control: None,
+            escape: None,
+            csi: None,
+            osc: None,
+            dcs: None,
+            resize: None,
+        }
+    }
 }
 
 extern "C" {
@@ -26,6 +50,9 @@ extern "C" {
         vt: *mut VTerm,
         callbacks: *const VTermParserCallbacks,
         user: *mut c_void,
-    ) -> *mut VTermState;
-    pub fn vterm_parser_get_callbacks(vt: *mut VTerm) -> *mut c_void;
+    );
+    pub fn vterm_parser_get_cbdata(vt: *mut VTerm) -> *mut c_void;
+
+    // These are in rusty_shims.c
+    pub fn vterm_parser_get_callbacks(vt: *mut VTerm) -> *const VTermParserCallbacks;
 }
@@ -14,7 +14,7 @@ pub enum VTermDamageSize {
 
 pub enum VTermAttrMask {}
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub struct VTermScreenCallbacks {
     pub damage: Option<extern "C" fn(VTermRect, *mut c_void) -> c_int>,
@@ -24,7 +24,7 @@ pub struct VTermScreenCallbacks {
     pub bell: Option<extern "C" fn(*mut c_void) -> c_int>,
     pub resize: Option<extern "C" fn(c_int, c_int, *mut c_void) -> c_int>,
     pub sb_pushline: Option<extern "C" fn(c_int, *const VTermScreenCell, *mut c_void) -> c_int>,
-    pub sb_popline: Option<extern "C" fn(c_int, *const VTermScreenCell, *mut c_void) -> c_int>,
+    pub sb_popline: Option<extern "C" fn(c_int, *mut VTermScreenCell, *mut c_void) -> c_int>,
 }
 
 impl Default for VTermScreenCallbacks {
@@ -56,7 +56,7 @@ extern "C" {
         screen: *mut VTermScreen,
         fallbacks: *const VTermParserCallbacks,
         user: *mut c_void,
-    ) -> *mut c_void;
+    );
     pub fn vterm_screen_get_unrecognised_fbdata(screen: *mut VTermScreen) -> *mut c_void;
 
     pub fn vterm_screen_enable_altscreen(screen: *mut VTermScreen, altscreen: c_int);
@@ -92,7 +92,15 @@ extern "C" {
         cell: *mut VTermScreenCell,
     ) -> c_int;
 
+    pub fn vterm_screen_get_flat_cells(
+        screen: *const VTermScreen,
+        rect: VTermRect,
+        cells: *mut VTermFlatCell,
+        len: size_t,
+    ) -> c_int;
+
     pub fn vterm_screen_is_eol(screen: *const VTermScreen, pos: VTermPos) -> c_int;
+    pub fn vterm_screen_mark_uris(screen: *const VTermScreen, marks: *mut u8, len: size_t);
     pub fn vterm_value_get_boolean(value: *const VTermValue) -> c_int;
     pub fn vterm_value_get_number(value: *const VTermValue) -> c_int;
     pub fn vterm_value_get_string(value: *const VTermValue) -> *const c_char;
@@ -181,7 +189,7 @@ mod tests {
     }
     extern "C" fn sb_popline_handler(
         _: c_int,
-        _: *const VTermScreenCell,
+        _: *mut VTermScreenCell,
         strings: *mut c_void,
     ) -> c_int {
         handler_helper("sb_popline".to_string(), strings);
@@ -3,6 +3,7 @@ use libc::{c_int, c_void};
 
 pub enum VTermState {}
 
+#[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub struct VTermStateCallbacks {
     pub put_glyph: Option<extern "C" fn(*mut VTermGlyphInfo, VTermPos, *mut c_void) -> c_int>,
@@ -82,12 +83,36 @@ extern "C" {
         attr: VTermAttr,
         val: *mut VTermValue,
     ) -> c_int;
+    pub fn vterm_state_set_penattr_uri(state: *mut VTermState, uri: c_int);
     pub fn vterm_state_set_termprop(
         state: *mut VTermState,
         prop: VTermProp,
         val: *mut VTermValue,
     ) -> c_int;
     pub fn vterm_state_get_lineinfo(state: *const VTermState, row: c_int) -> *const VTermLineInfo;
+
+    // These are my rust ffi workarounds for state that libvterm doesn't expose
+    pub fn vterm_state_get_mouse_flags(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_bracketpaste(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_cursor_visible(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_cursor_shape(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_altscreen(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_pen_fg_index(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_pen_bg_index(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_pen_cell(state: *const VTermState, cell: *mut VTermFlatCell);
+    pub fn vterm_state_get_keypad(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_cursor_keys(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_autowrap(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_insert(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_newline(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_cursor_blink(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_origin(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_reverse(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_leftrightmargin(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_mouse_protocol(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_at_phantom(state: *const VTermState) -> c_int;
+    pub fn vterm_state_get_scrollregion(state: *const VTermState, rect: *mut VTermRect);
+    pub fn vterm_state_get_tabstop(state: *const VTermState, col: c_int) -> c_int;
 }
 
 mod tests {
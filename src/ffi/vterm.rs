@@ -1,4 +1,3 @@
-use super::{VTermKey, VTermModifier};
 use libc::{c_char, c_int, c_uchar, size_t};
 
 pub enum VTerm {}
@@ -21,19 +20,23 @@ extern "C" {
 
     pub fn vterm_output_read(vt: *mut VTerm, buffer: *mut c_char, len: size_t) -> size_t;
 
-    pub fn vterm_keyboard_unichar(vt: *mut VTerm, c: u32, modifier: VTermModifier);
-    pub fn vterm_keyboard_key(vt: *mut VTerm, key: VTermKey, modifier: VTermModifier);
+    // Internal to libvterm, but exported. It is how the state layer answers queries.
+    pub fn vterm_push_output_bytes(vt: *mut VTerm, bytes: *const c_char, len: size_t);
+    // Also internal. How many columns a character takes, and whether it combines with the one
+    // before it instead.
+    pub fn vterm_unicode_width(codepoint: c_int) -> c_int;
+    pub fn vterm_unicode_is_combining(codepoint: c_int) -> c_int;
+
+    // key and modifier are passed as ints since VTermKey covers a range of function keys and
+    // VTermModifier values are or'ed together, neither of which a rust enum can hold.
+    pub fn vterm_keyboard_unichar(vt: *mut VTerm, c: u32, modifier: c_int);
+    pub fn vterm_keyboard_key(vt: *mut VTerm, key: c_int, modifier: c_int);
 
     pub fn vterm_keyboard_start_paste(vt: *mut VTerm);
     pub fn vterm_keyboard_end_paste(vt: *mut VTerm);
 
-    pub fn vterm_mouse_move(vt: *mut VTerm, row: c_int, col: c_int, modifier: VTermModifier);
-    pub fn vterm_mouse_button(
-        vt: *mut VTerm,
-        button: c_int,
-        pressed: bool,
-        modifier: VTermModifier,
-    );
+    pub fn vterm_mouse_move(vt: *mut VTerm, row: c_int, col: c_int, modifier: c_int);
+    pub fn vterm_mouse_button(vt: *mut VTerm, button: c_int, pressed: bool, modifier: c_int);
 }
 
 mod tests {
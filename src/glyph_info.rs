@@ -5,7 +5,8 @@ use super::*;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct GlyphInfo {
-    pub chars: Vec<u8>,
+    /// The base character followed by any combining characters
+    pub chars: Vec<char>,
     pub width: isize,
     pub protected_cell: bool,
     pub dwl: bool, // On a DECDWL or DECDHL line
@@ -13,31 +14,37 @@ pub struct GlyphInfo {
 }
 
 impl GlyphInfo {
-    pub fn from_ptr(ptr: *const ffi::VTermGlyphInfo) -> GlyphInfo {
-        let mut buf = [0 as u32; ffi::VTERM_MAX_CHARS_PER_CELL];
-        let chars_count = unsafe {
-            ffi::vterm_glyph_info_get_chars(
-                ptr,
-                buf.as_mut_ptr(),
-                ffi::VTERM_MAX_CHARS_PER_CELL as size_t,
-            )
-        };
-        let buf: [u8; ffi::VTERM_MAX_CHARS_PER_CELL * 4] = unsafe { ::std::mem::transmute(buf) };
-        let mut chars: Vec<u8> = vec![];
-        chars.extend_from_slice(&buf[0..chars_count as usize * 4]);
+    /// Read the glyph libvterm passes to put_glyph. The pointer must point to a valid
+    /// `VTermGlyphInfo`.
+    pub(crate) unsafe fn from_ptr(ptr: *const ffi::VTermGlyphInfo) -> GlyphInfo {
+        let mut buf = [0u32; ffi::VTERM_MAX_CHARS_PER_CELL];
+        let chars_count = ffi::vterm_glyph_info_get_chars(
+            ptr,
+            buf.as_mut_ptr(),
+            ffi::VTERM_MAX_CHARS_PER_CELL as size_t,
+        );
+        let chars = buf[0..chars_count as usize]
+            .iter()
+            .filter_map(|&c| ::std::char::from_u32(c))
+            .collect();
 
-        unsafe {
-            GlyphInfo {
-                chars: chars,
-                width: ffi::vterm_glyph_info_width(ptr) as isize,
-                protected_cell: int_to_bool(ffi::vterm_glyph_info_width(ptr) as i32),
-                dwl: int_to_bool(ffi::vterm_glyph_info_width(ptr)),
-                dhl: ffi::vterm_glyph_info_width(ptr) as u8,
-            }
+        GlyphInfo {
+            chars: chars,
+            width: ffi::vterm_glyph_info_width(ptr) as isize,
+            protected_cell: int_to_bool(ffi::vterm_glyph_info_protected_cell(ptr) as i32),
+            dwl: int_to_bool(ffi::vterm_glyph_info_dwl(ptr) as i32),
+            dhl: ffi::vterm_glyph_info_dhl(ptr) as u8,
         }
     }
 }
 
+impl GlyphInfo {
+    /// The glyph's text, with any combining characters after the base character
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+}
+
 impl Default for GlyphInfo {
     fn default() -> GlyphInfo {
         GlyphInfo {
@@ -0,0 +1,312 @@
+use libc::{c_int, size_t};
+use std::collections::HashMap;
+
+use super::*;
+
+/// An OSC 8 hyperlink
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Hyperlink {
+    pub uri: String,
+    /// The `id` parameter. Programs give separate runs of cells the same id to mark them as one
+    /// link, like a url wrapped over two lines.
+    pub id: Option<String>,
+}
+
+impl Hyperlink {
+    /// Parse the part of an OSC 8 after the `8;`, which is `params;uri`. None means the link
+    /// is closed, which an empty uri does.
+    fn from_osc8(data: &[u8]) -> Option<Hyperlink> {
+        let split = data.iter().position(|&b| b == b';')?;
+        let (params, uri) = (&data[..split], &data[split + 1..]);
+        if uri.is_empty() {
+            return None;
+        }
+
+        let id = params
+            .split(|&b| b == b':')
+            .find(|param| param.starts_with(b"id="))
+            .map(|param| String::from_utf8_lossy(&param[3..]).into_owned());
+
+        Some(Hyperlink {
+            uri: String::from_utf8_lossy(uri).into_owned(),
+            id: id,
+        })
+    }
+}
+
+/// A run of cells on one row that belong to the same hyperlink
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperlinkSpan {
+    pub hyperlink: Hyperlink,
+    /// Always one row high
+    pub rect: Rect,
+}
+
+/// The hyperlinks cells can point at. Cells store an index into this, offset by one since 0
+/// means no link. Each distinct link is only stored once, and once the table grows past
+/// `sweep_at` the ids no cell or pen still uses are freed for reuse.
+#[derive(Debug)]
+pub(crate) struct Hyperlinks {
+    links: Vec<Option<Hyperlink>>,
+    ids: HashMap<Hyperlink, c_int>,
+    free: Vec<c_int>,
+    sweep_at: usize,
+}
+
+/// How many links the table holds before the first sweep
+const MIN_SWEEP_AT: usize = 256;
+
+impl Default for Hyperlinks {
+    fn default() -> Hyperlinks {
+        Hyperlinks {
+            links: vec![],
+            ids: HashMap::new(),
+            free: vec![],
+            sweep_at: MIN_SWEEP_AT,
+        }
+    }
+}
+
+impl Hyperlinks {
+    pub fn intern(&mut self, link: &Hyperlink) -> c_int {
+        if let Some(&id) = self.ids.get(link) {
+            return id;
+        }
+
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.links[id as usize - 1] = Some(link.clone());
+                id
+            }
+            None => {
+                self.links.push(Some(link.clone()));
+                self.links.len() as c_int
+            }
+        };
+        self.ids.insert(link.clone(), id);
+        id
+    }
+
+    pub fn get(&self, id: c_int) -> Option<&Hyperlink> {
+        if id <= 0 {
+            return None;
+        }
+        self.links.get(id as usize - 1)?.as_ref()
+    }
+
+    /// Free the ids no cell on either screen or pen uses. Scrollback resolves its links when
+    /// lines are pushed, so doesn't hold ids.
+    fn sweep(&mut self, screen: &ffi::VTermScreen) {
+        let mut marks = vec![0u8; self.links.len() + 1];
+        unsafe { ffi::vterm_screen_mark_uris(screen, marks.as_mut_ptr(), marks.len() as size_t) };
+
+        for (index, slot) in self.links.iter_mut().enumerate() {
+            let id = index as c_int + 1;
+            if marks[id as usize] != 0 {
+                continue;
+            }
+            if let Some(link) = slot.take() {
+                self.ids.remove(&link);
+                self.free.push(id);
+            }
+        }
+
+        self.sweep_at = MIN_SWEEP_AT.max(self.ids.len() * 2);
+    }
+
+    /// Open or close a link from an OSC 8. Cells written from now on get the link.
+    pub fn handle_osc8(
+        &mut self,
+        state: &mut ffi::VTermState,
+        screen: &ffi::VTermScreen,
+        data: &[u8],
+    ) {
+        let id = match Hyperlink::from_osc8(data) {
+            Some(link) => {
+                if !self.ids.contains_key(&link) && self.ids.len() >= self.sweep_at {
+                    self.sweep(screen);
+                }
+                self.intern(&link)
+            }
+            None => 0,
+        };
+        unsafe { ffi::vterm_state_set_penattr_uri(state, id) };
+    }
+}
+
+impl VTerm {
+    /// Return the hyperlinked runs of cells in the rect. Panics if the rect is off the screen, see
+    /// `try_screen_get_hyperlinks`.
+    pub fn screen_get_hyperlinks(&self, rect: &Rect) -> Vec<HyperlinkSpan> {
+        self.try_screen_get_hyperlinks(rect)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `screen_get_hyperlinks` but returns an error if the rect is off the screen
+    pub fn try_screen_get_hyperlinks(&self, rect: &Rect) -> Result<Vec<HyperlinkSpan>, Error> {
+        check_rect(self.get_size(), rect)?;
+
+        let cell_buf = unsafe { ffi::vterm_cell_new() };
+        if cell_buf.is_null() {
+            return Err(Error::AllocationFailed);
+        }
+
+        let hyperlinks = &unsafe { self.callback_data.as_ref() }.hyperlinks;
+        let mut spans: Vec<HyperlinkSpan> = vec![];
+        for y in rect.top()..rect.bottom() {
+            // The link id and start column of the span being built
+            let mut current: Option<(c_int, usize)> = None;
+
+            for x in rect.left()..rect.right() {
+                let mut chars = [0u32; ffi::VTERM_MAX_CHARS_PER_CELL];
+                let id = unsafe {
+                    ffi::vterm_screen_get_cell(
+                        self.screen_ptr.as_ref(),
+                        ffi::VTermPos::from_pos(&Pos::new(x, y)),
+                        cell_buf,
+                    );
+                    ffi::vterm_cell_get_chars(
+                        cell_buf,
+                        chars.as_mut_ptr(),
+                        ffi::VTERM_MAX_CHARS_PER_CELL as size_t,
+                    );
+                    ffi::vterm_cell_get_uri(cell_buf)
+                };
+
+                // The right half of a wide character belongs to whatever its left half does
+                if chars[0] == 0xffff_ffff {
+                    continue;
+                }
+                if current.map(|(current_id, _)| current_id) == Some(id) {
+                    continue;
+                }
+
+                push_span(&mut spans, hyperlinks, current, x, y);
+                current = if id == 0 { None } else { Some((id, x)) };
+            }
+
+            push_span(&mut spans, hyperlinks, current, rect.right(), y);
+        }
+
+        unsafe { ffi::vterm_cell_free(cell_buf) };
+
+        Ok(spans)
+    }
+}
+
+fn push_span(
+    spans: &mut Vec<HyperlinkSpan>,
+    hyperlinks: &Hyperlinks,
+    current: Option<(c_int, usize)>,
+    end: usize,
+    y: usize,
+) {
+    let (id, start) = match current {
+        Some(current) => current,
+        None => return,
+    };
+
+    if let Some(hyperlink) = hyperlinks.get(id) {
+        spans.push(HyperlinkSpan {
+            hyperlink: hyperlink.clone(),
+            rect: Rect::new(Pos::new(start, y), Size::new(end - start, 1)),
+        });
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::super::*;
+    use super::*;
+    use std::io::prelude::*;
+
+    #[test]
+    fn hyperlink_from_osc8_reads_the_id_and_uri() {
+        assert_eq!(
+            Hyperlink::from_osc8(b"foo=bar:id=1;http://a/b;c"),
+            Some(Hyperlink {
+                uri: "http://a/b;c".to_string(),
+                id: Some("1".to_string()),
+            })
+        );
+        assert_eq!(
+            Hyperlink::from_osc8(b";file:///tmp"),
+            Some(Hyperlink {
+                uri: "file:///tmp".to_string(),
+                id: None,
+            })
+        );
+        assert_eq!(Hyperlink::from_osc8(b";"), None);
+        assert_eq!(Hyperlink::from_osc8(b"http://no/params"), None);
+    }
+
+    #[test]
+    fn hyperlinks_interns_links() {
+        let mut hyperlinks: Hyperlinks = Default::default();
+        let a = Hyperlink {
+            uri: "a".to_string(),
+            id: None,
+        };
+        let b = Hyperlink {
+            uri: "a".to_string(),
+            id: Some("1".to_string()),
+        };
+
+        assert_eq!(hyperlinks.intern(&a), 1);
+        assert_eq!(hyperlinks.intern(&b), 2);
+        assert_eq!(hyperlinks.intern(&a), 1);
+        assert_eq!(hyperlinks.get(2), Some(&b));
+        assert_eq!(hyperlinks.get(0), None);
+    }
+
+    #[test]
+    fn hyperlinks_frees_links_nothing_uses() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 10,
+        })
+        .unwrap();
+        vterm.screen_set_scrollback(Scrollback::with_max_lines(10));
+
+        for n in 0..5000 {
+            let line = format!("\x1b]8;;http://a/{}\x1b\\x\x1b]8;;\x1b\\\r\n", n);
+            vterm.write(line.as_bytes()).unwrap();
+        }
+
+        let hyperlinks = &unsafe { vterm.callback_data.as_ref() }.hyperlinks;
+        assert!(hyperlinks.links.len() <= MIN_SWEEP_AT + 1);
+        assert_eq!(
+            vterm.screen_get_cell(&Pos::new(0, 0)).hyperlink,
+            Some(Hyperlink {
+                uri: "http://a/4999".to_string(),
+                id: None,
+            })
+        );
+        let text: Vec<_> = vterm
+            .scrollback_lines()
+            .map(|line| line.cells()[0].hyperlink.clone().unwrap().uri)
+            .collect();
+        assert_eq!(text.last().unwrap(), "http://a/4998");
+
+        // Links on the screen that's switched away from are kept
+        vterm.screen_enable_altscreen(true);
+        vterm
+            .write(b"\x1b[H\x1b]8;;http://keep\x1b\\k\x1b]8;;\x1b\\\x1b[?1049h")
+            .unwrap();
+        for n in 0..5000 {
+            let line = format!("\x1b]8;;http://b/{}\x1b\\x\x1b]8;;\x1b\\\r\n", n);
+            vterm.write(line.as_bytes()).unwrap();
+        }
+        vterm.write(b"\x1b[?1049l").unwrap();
+
+        let hyperlinks = &unsafe { vterm.callback_data.as_ref() }.hyperlinks;
+        assert!(hyperlinks.links.len() <= MIN_SWEEP_AT + 1);
+        assert_eq!(
+            vterm.screen_get_cell(&Pos::new(0, 0)).hyperlink,
+            Some(Hyperlink {
+                uri: "http://keep".to_string(),
+                id: None,
+            })
+        );
+    }
+}
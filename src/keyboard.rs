@@ -0,0 +1,224 @@
+use libc::c_int;
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
+use super::*;
+
+/// Keyboard modifiers held while a key, character or mouse event is sent. Combine them with `|`,
+/// like `Modifiers::SHIFT | Modifiers::CTRL`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Modifiers {
+    bits: u8,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers { bits: 0x00 };
+    pub const SHIFT: Modifiers = Modifiers { bits: 0x01 };
+    pub const ALT: Modifiers = Modifiers { bits: 0x02 };
+    pub const CTRL: Modifiers = Modifiers { bits: 0x04 };
+
+    /// The raw bits as libvterm's VTermModifier expects them
+    pub fn bits(&self) -> u8 {
+        self.bits
+    }
+
+    /// Build from raw bits, dropping any that don't correspond to a modifier
+    pub fn from_bits_truncate(bits: u8) -> Modifiers {
+        Modifiers {
+            bits: bits & (Modifiers::SHIFT.bits | Modifiers::ALT.bits | Modifiers::CTRL.bits),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    pub fn contains(&self, other: Modifiers) -> bool {
+        self.bits & other.bits == other.bits
+    }
+
+    pub fn insert(&mut self, other: Modifiers) {
+        self.bits |= other.bits;
+    }
+
+    pub fn remove(&mut self, other: Modifiers) {
+        self.bits &= !other.bits;
+    }
+
+    pub fn as_c_int(&self) -> c_int {
+        self.bits as c_int
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Modifiers;
+
+    fn bitor(self, other: Modifiers) -> Modifiers {
+        Modifiers {
+            bits: self.bits | other.bits,
+        }
+    }
+}
+
+impl BitOrAssign for Modifiers {
+    fn bitor_assign(&mut self, other: Modifiers) {
+        self.bits |= other.bits;
+    }
+}
+
+impl BitAnd for Modifiers {
+    type Output = Modifiers;
+
+    fn bitand(self, other: Modifiers) -> Modifiers {
+        Modifiers {
+            bits: self.bits & other.bits,
+        }
+    }
+}
+
+/// Non-character keys that libvterm knows how to encode.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Key {
+    Enter,
+    Tab,
+    Backspace,
+    Escape,
+
+    Up,
+    Down,
+    Left,
+    Right,
+
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+
+    /// Function keys F1 through F255. libvterm only has encodings for F1 to F12, others are
+    /// silently ignored.
+    Function(u8),
+
+    Kp0,
+    Kp1,
+    Kp2,
+    Kp3,
+    Kp4,
+    Kp5,
+    Kp6,
+    Kp7,
+    Kp8,
+    Kp9,
+    KpMult,
+    KpPlus,
+    KpComma,
+    KpMinus,
+    KpPeriod,
+    KpDivide,
+    KpEnter,
+    KpEqual,
+}
+
+impl Key {
+    /// The value of the matching VTermKey
+    pub fn as_c_int(&self) -> c_int {
+        use crate::ffi::VTermKey::*;
+
+        match *self {
+            Key::Enter => VTERM_KEY_ENTER as c_int,
+            Key::Tab => VTERM_KEY_TAB as c_int,
+            Key::Backspace => VTERM_KEY_BACKSPACE as c_int,
+            Key::Escape => VTERM_KEY_ESCAPE as c_int,
+
+            Key::Up => VTERM_KEY_UP as c_int,
+            Key::Down => VTERM_KEY_DOWN as c_int,
+            Key::Left => VTERM_KEY_LEFT as c_int,
+            Key::Right => VTERM_KEY_RIGHT as c_int,
+
+            Key::Insert => VTERM_KEY_INS as c_int,
+            Key::Delete => VTERM_KEY_DEL as c_int,
+            Key::Home => VTERM_KEY_HOME as c_int,
+            Key::End => VTERM_KEY_END as c_int,
+            Key::PageUp => VTERM_KEY_PAGEUP as c_int,
+            Key::PageDown => VTERM_KEY_PAGEDOWN as c_int,
+
+            Key::Function(n) => VTERM_KEY_FUNCTION_0 as c_int + n as c_int,
+
+            Key::Kp0 => VTERM_KEY_KP_0 as c_int,
+            Key::Kp1 => VTERM_KEY_KP_1 as c_int,
+            Key::Kp2 => VTERM_KEY_KP_2 as c_int,
+            Key::Kp3 => VTERM_KEY_KP_3 as c_int,
+            Key::Kp4 => VTERM_KEY_KP_4 as c_int,
+            Key::Kp5 => VTERM_KEY_KP_5 as c_int,
+            Key::Kp6 => VTERM_KEY_KP_6 as c_int,
+            Key::Kp7 => VTERM_KEY_KP_7 as c_int,
+            Key::Kp8 => VTERM_KEY_KP_8 as c_int,
+            Key::Kp9 => VTERM_KEY_KP_9 as c_int,
+            Key::KpMult => VTERM_KEY_KP_MULT as c_int,
+            Key::KpPlus => VTERM_KEY_KP_PLUS as c_int,
+            Key::KpComma => VTERM_KEY_KP_COMMA as c_int,
+            Key::KpMinus => VTERM_KEY_KP_MINUS as c_int,
+            Key::KpPeriod => VTERM_KEY_KP_PERIOD as c_int,
+            Key::KpDivide => VTERM_KEY_KP_DIVIDE as c_int,
+            Key::KpEnter => VTERM_KEY_KP_ENTER as c_int,
+            Key::KpEqual => VTERM_KEY_KP_EQUAL as c_int,
+        }
+    }
+}
+
+impl VTerm {
+    /// Send a key press to the terminal. The encoded bytes are placed in the output buffer.
+    pub fn keyboard_key(&mut self, key: Key, mods: Modifiers) {
+        unsafe { ffi::vterm_keyboard_key(self.ptr.as_mut(), key.as_c_int(), mods.as_c_int()) }
+    }
+
+    /// Send a unicode character to the terminal. The encoded bytes are placed in the output
+    /// buffer.
+    pub fn keyboard_char(&mut self, c: char, mods: Modifiers) {
+        unsafe { ffi::vterm_keyboard_unichar(self.ptr.as_mut(), c as u32, mods.as_c_int()) }
+    }
+
+    /// Returns true when the program has turned on bracketed paste mode (DECSET 2004).
+    pub fn keyboard_is_bracketed_paste(&self) -> bool {
+        unsafe { int_to_bool(ffi::vterm_state_get_bracketpaste(self.state_ptr.as_ref())) }
+    }
+
+    /// Send pasted text to the terminal. When the program has enabled bracketed paste the text is
+    /// wrapped in paste markers, and ESC and C1 CSI characters are dropped so the text can't end
+    /// the paste early with its own `ESC [ 201 ~`.
+    pub fn paste(&mut self, text: &str) {
+        let is_bracketed = self.keyboard_is_bracketed_paste();
+
+        unsafe { ffi::vterm_keyboard_start_paste(self.ptr.as_mut()) };
+
+        for c in text.chars() {
+            if is_bracketed && (c == '\x1b' || c == '\u{9b}') {
+                continue;
+            }
+            self.keyboard_char(c, Modifiers::NONE);
+        }
+
+        unsafe { ffi::vterm_keyboard_end_paste(self.ptr.as_mut()) };
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::super::*;
+
+    #[test]
+    fn modifiers_can_combine() {
+        let mods = Modifiers::SHIFT | Modifiers::CTRL;
+        assert!(mods.contains(Modifiers::SHIFT));
+        assert!(mods.contains(Modifiers::CTRL));
+        assert!(!mods.contains(Modifiers::ALT));
+        assert_eq!(mods.bits(), 0x05);
+    }
+
+    #[test]
+    fn key_function_maps_to_vterm_key_function() {
+        assert_eq!(Key::Function(1).as_c_int(), 257);
+        assert_eq!(Key::Function(255).as_c_int(), 511);
+        assert_eq!(Key::Kp0.as_c_int(), 512);
+    }
+}
@@ -6,26 +6,62 @@ use libc::c_int;
 
 pub mod ffi;
 
+mod base64;
+mod clipboard;
+mod error;
 mod events;
 mod geom;
 mod glyph_info;
+mod hyperlink;
+mod keyboard;
+mod line_info;
+mod mouse;
+mod palette;
+mod parser;
+mod parser_callbacks;
+mod parser_handler;
+mod renderer;
 mod screen;
 mod screen_callbacks;
 mod screen_cell;
+mod screen_handler;
+mod scrollback;
+mod serialize;
+mod snapshot;
 mod state;
 mod state_callbacks;
+mod state_handler;
+mod unrecognised;
+mod unrecognised_callbacks;
+mod unrecognised_handler;
 mod vterm;
 
+pub use clipboard::*;
+pub use error::*;
 pub use events::*;
 pub use geom::*;
 pub use glyph_info::*;
+pub use hyperlink::*;
+pub use keyboard::*;
+pub use line_info::*;
+pub use mouse::*;
+pub use palette::*;
+pub use parser::*;
+pub use parser_handler::*;
+pub use renderer::*;
 pub use screen::*;
 pub use screen_cell::*;
+pub use screen_handler::*;
+pub use scrollback::*;
+pub use snapshot::*;
 pub use state::*;
+pub use state_handler::*;
+pub use unrecognised::*;
+pub use unrecognised_handler::*;
 pub use vterm::*;
 
 mod prelude {
-    pub use crate::{ColorPalette, ColorRGB, GlyphInfo, Pos, Rect, ScreenCell, Size};
+    pub use crate::{ColorPalette, ColorRGB, GlyphInfo, LineInfo, Pos, Rect, ScreenCell, Size};
 }
 
 pub fn int_to_bool(val: c_int) -> bool {
@@ -0,0 +1,130 @@
+use libc::c_int;
+
+use super::*;
+
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum DoubleHeight {
+    #[default]
+    None = 0,
+    Top,
+    Bottom,
+}
+
+impl DoubleHeight {
+    pub fn from_u32(val: u32) -> DoubleHeight {
+        match val {
+            1 => DoubleHeight::Top,
+            2 => DoubleHeight::Bottom,
+            _ => DoubleHeight::None,
+        }
+    }
+}
+
+/// How a row is drawn, as set by DECDWL, DECDHL and DECSWL, and whether it carries on from the
+/// row above.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct LineInfo {
+    /// On a DECDWL or DECDHL line
+    pub double_width: bool,
+    pub double_height: DoubleHeight,
+    /// Text wrapped onto this row from the end of the one above
+    pub continuation: bool,
+}
+
+impl LineInfo {
+    /// Read the line info libvterm passes around. The pointer must point to a valid
+    /// `VTermLineInfo`.
+    pub(crate) unsafe fn from_ptr(ptr: *const ffi::VTermLineInfo) -> LineInfo {
+        LineInfo {
+            double_width: int_to_bool(ffi::vterm_line_info_get_doublewidth(ptr) as i32),
+            double_height: DoubleHeight::from_u32(ffi::vterm_line_info_get_doubleheight(ptr)),
+            continuation: int_to_bool(ffi::vterm_line_info_get_continuation(ptr) as i32),
+        }
+    }
+}
+
+impl VTerm {
+    /// Returns the line info for the given row. Panics if the row is off the screen, see
+    /// `try_state_get_line_info`.
+    pub fn state_get_line_info(&self, row: usize) -> LineInfo {
+        self.try_state_get_line_info(row)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Returns the line info for the given row, or an error if it's off the screen
+    pub fn try_state_get_line_info(&self, row: usize) -> Result<LineInfo, Error> {
+        check_pos(self.get_size(), &Pos::new(0, row))?;
+
+        let ptr = unsafe { ffi::vterm_state_get_lineinfo(self.state_ptr.as_ref(), row as c_int) };
+        Ok(unsafe { LineInfo::from_ptr(ptr) })
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::super::*;
+    use std::io::prelude::*;
+
+    #[test]
+    fn state_can_get_line_info() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 5,
+        })
+        .unwrap();
+        assert_eq!(vterm.state_get_line_info(0), LineInfo::default());
+
+        vterm.write(b"\x1b#3\r\n\x1b#6").unwrap();
+
+        assert_eq!(
+            vterm.state_get_line_info(0),
+            LineInfo {
+                double_width: true,
+                double_height: DoubleHeight::Top,
+                continuation: false,
+            }
+        );
+        assert_eq!(
+            vterm.state_get_line_info(1),
+            LineInfo {
+                double_width: true,
+                double_height: DoubleHeight::None,
+                continuation: false,
+            }
+        );
+    }
+
+    #[test]
+    fn state_can_get_line_continuation() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 3,
+            width: 5,
+        })
+        .unwrap();
+        vterm.write(b"abcdefg\r\nh").unwrap();
+
+        assert!(!vterm.state_get_line_info(0).continuation);
+        assert!(vterm.state_get_line_info(1).continuation);
+        assert!(!vterm.state_get_line_info(2).continuation);
+
+        // Erasing the end of the row above breaks the wrap
+        vterm.write(b"\x1b[1;3H\x1b[K").unwrap();
+        assert!(!vterm.state_get_line_info(1).continuation);
+    }
+
+    #[test]
+    fn state_try_get_line_info_returns_error_if_out_of_bounds() {
+        let vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 5,
+        })
+        .unwrap();
+        assert_eq!(
+            vterm.try_state_get_line_info(2),
+            Err(Error::OutOfBounds {
+                size: Size::new(5, 2),
+                pos: Pos::new(0, 2),
+            })
+        );
+    }
+}
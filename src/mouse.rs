@@ -0,0 +1,47 @@
+use libc::c_int;
+
+use super::*;
+
+/// Mouse buttons that libvterm can report to the program running in the terminal.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum MouseButton {
+    Left = 1,
+    Middle,
+    Right,
+    WheelUp,
+    WheelDown,
+}
+
+impl MouseButton {
+    pub fn as_c_int(&self) -> c_int {
+        *self as c_int
+    }
+}
+
+impl VTerm {
+    /// Tell the terminal the mouse moved to the given position. Output is only generated if the
+    /// program has asked for drag or move events, in the encoding it asked for.
+    pub fn mouse_move(&mut self, pos: &Pos, mods: Modifiers) {
+        unsafe {
+            ffi::vterm_mouse_move(
+                self.ptr.as_mut(),
+                pos.y as c_int,
+                pos.x as c_int,
+                mods.as_c_int(),
+            )
+        }
+    }
+
+    /// Tell the terminal a mouse button was pressed or released at the last position given to
+    /// `mouse_move`. Output is only generated if the program has enabled mouse tracking.
+    pub fn mouse_button(&mut self, button: MouseButton, pressed: bool, mods: Modifiers) {
+        unsafe {
+            ffi::vterm_mouse_button(
+                self.ptr.as_mut(),
+                button.as_c_int(),
+                pressed,
+                mods.as_c_int(),
+            )
+        }
+    }
+}
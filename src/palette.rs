@@ -0,0 +1,484 @@
+use libc::c_int;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::*;
+
+/// Every colour a program can pick by index, plus the default foreground and background
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    /// The 16 ANSI colours, then the 216 colour cube and 24 greys, as `CSI 38;5;n m` indexes them
+    pub colors: [ColorRGB; 256],
+    pub default_fg: ColorRGB,
+    pub default_bg: ColorRGB,
+}
+
+/// libvterm's own colours, which is what a new VTerm starts with
+impl Default for Palette {
+    fn default() -> Palette {
+        const ANSI: [(u8, u8, u8); 16] = [
+            (0, 0, 0),
+            (224, 0, 0),
+            (0, 224, 0),
+            (224, 224, 0),
+            (0, 0, 224),
+            (224, 0, 224),
+            (0, 224, 224),
+            (224, 224, 224),
+            (128, 128, 128),
+            (255, 64, 64),
+            (64, 255, 64),
+            (255, 255, 64),
+            (64, 64, 255),
+            (255, 64, 255),
+            (64, 255, 255),
+            (255, 255, 255),
+        ];
+        const RAMP6: [u8; 6] = [0x00, 0x33, 0x66, 0x99, 0xcc, 0xff];
+        const RAMP24: [u8; 24] = [
+            0x00, 0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x85, 0x90,
+            0x9b, 0xa6, 0xb1, 0xbc, 0xc7, 0xd2, 0xdd, 0xe8, 0xf3, 0xff,
+        ];
+
+        let colors = ::std::array::from_fn(|index| match index {
+            0..=15 => {
+                let (red, green, blue) = ANSI[index];
+                rgb(red, green, blue)
+            }
+            16..=231 => {
+                let index = index - 16;
+                rgb(RAMP6[index / 36], RAMP6[index / 6 % 6], RAMP6[index % 6])
+            }
+            _ => {
+                let grey = RAMP24[index - 232];
+                rgb(grey, grey, grey)
+            }
+        });
+
+        Palette {
+            colors: colors,
+            default_fg: rgb(240, 240, 240),
+            default_bg: rgb(0, 0, 0),
+        }
+    }
+}
+
+impl Palette {
+    /// Read `colorN`, `foreground` and `background` resources, like `*.color4: #0000ee` or
+    /// `URxvt.background: rgb:00/00/00`, on top of the default palette. Comments, preprocessor
+    /// lines and other resources are skipped.
+    pub fn from_xresources(text: &str) -> Result<Palette, Error> {
+        let mut palette: Palette = Default::default();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('!') || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.split_once(':') {
+                Some(pair) => pair,
+                None => continue,
+            };
+
+            // The resource name is the last component, whatever class or wildcard comes first
+            let name = key.trim().rsplit(['.', '*']).next().unwrap_or("");
+            let color = match palette.color_mut(name) {
+                Some(color) => color,
+                None => continue,
+            };
+            *color = parse_color(value.trim()).ok_or(Error::InvalidTheme { line: number + 1 })?;
+        }
+
+        Ok(palette)
+    }
+
+    /// Write the palette as `*.foreground`, `*.background` and `*.colorN` resources. The first 16
+    /// colours are always written, and the rest only when they differ from the default.
+    pub fn to_xresources(&self) -> String {
+        let default: Palette = Default::default();
+        let mut text = String::new();
+
+        writeln!(text, "*.foreground: {}", hex(&self.default_fg)).unwrap();
+        writeln!(text, "*.background: {}", hex(&self.default_bg)).unwrap();
+        for (index, color) in self.colors.iter().enumerate() {
+            if index < 16 || *color != default.colors[index] {
+                writeln!(text, "*.color{}: {}", index, hex(color)).unwrap();
+            }
+        }
+
+        text
+    }
+
+    /// Read a base16 scheme's `base00` to `base0F` keys, laid out the way base16-shell does.
+    /// That also sets colours 16 to 21 from the extra shades, so they no longer match the cube.
+    /// Missing keys leave the default palette's colour alone.
+    pub fn from_base16(text: &str) -> Result<Palette, Error> {
+        let mut palette: Palette = Default::default();
+
+        for (number, line) in text.lines().enumerate() {
+            let (key, value) = match line.trim().split_once(':') {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let base = match key.trim().strip_prefix("base") {
+                Some(digits) if digits.len() == 2 => match u8::from_str_radix(digits, 16) {
+                    Ok(base) if base < 16 => base,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+
+            let value = value
+                .split_whitespace()
+                .next()
+                .unwrap_or("")
+                .trim_matches('"');
+            let value = value.strip_prefix('#').unwrap_or(value);
+            let color = parse_color(&format!("#{}", value))
+                .ok_or(Error::InvalidTheme { line: number + 1 })?;
+
+            for &(index, _) in BASE16_LAYOUT.iter().filter(|&&(_, b)| b == base) {
+                palette.colors[index] = color.clone();
+            }
+            match base {
+                0x00 => palette.default_bg = color,
+                0x05 => palette.default_fg = color,
+                _ => {}
+            }
+        }
+
+        Ok(palette)
+    }
+
+    /// Write the palette as a base16 scheme with the given name, the reverse of `from_base16`
+    pub fn to_base16(&self, scheme: &str) -> String {
+        let mut text = String::new();
+
+        writeln!(text, "scheme: \"{}\"", scheme).unwrap();
+        for base in 0..16 {
+            let &(index, _) = BASE16_LAYOUT.iter().find(|&&(_, b)| b == base).unwrap();
+            let color = hex(&self.colors[index]);
+            writeln!(text, "base{:02X}: \"{}\"", base, &color[1..]).unwrap();
+        }
+
+        text
+    }
+
+    fn color_mut(&mut self, name: &str) -> Option<&mut ColorRGB> {
+        match name {
+            "foreground" => Some(&mut self.default_fg),
+            "background" => Some(&mut self.default_bg),
+            _ => {
+                let index: u8 = name.strip_prefix("color")?.parse().ok()?;
+                Some(&mut self.colors[index as usize])
+            }
+        }
+    }
+}
+
+/// Finds the palette index of a colour, since libvterm only keeps the rgb of cells and pens. The
+/// table is built on first use, so it has to be invalidated whenever the palette changes.
+#[derive(Debug, Default)]
+pub(crate) struct PaletteLookup {
+    indexes: RefCell<Option<HashMap<ColorRGB, usize>>>,
+}
+
+impl PaletteLookup {
+    /// The lowest index with the colour, or 0 when no entry has it
+    pub fn get(&self, state: &ffi::VTermState, color: &ColorRGB) -> usize {
+        let mut indexes = self.indexes.borrow_mut();
+        let indexes = indexes.get_or_insert_with(|| {
+            let mut indexes = HashMap::with_capacity(256);
+            for index in (0..256).rev() {
+                indexes.insert(get_rgb_color_from_palette(state, index), index);
+            }
+            indexes
+        });
+
+        indexes.get(color).cloned().unwrap_or(0)
+    }
+
+    pub fn invalidate(&self) {
+        *self.indexes.borrow_mut() = None;
+    }
+}
+
+/// Which base16 colour each palette index gets, as base16-shell lays them out. Where a colour is
+/// used twice, its first index is the one written back out.
+const BASE16_LAYOUT: [(usize, u8); 22] = [
+    (0, 0x00),
+    (1, 0x08),
+    (2, 0x0b),
+    (3, 0x0a),
+    (4, 0x0d),
+    (5, 0x0e),
+    (6, 0x0c),
+    (7, 0x05),
+    (8, 0x03),
+    (9, 0x08),
+    (10, 0x0b),
+    (11, 0x0a),
+    (12, 0x0d),
+    (13, 0x0e),
+    (14, 0x0c),
+    (15, 0x07),
+    (16, 0x09),
+    (17, 0x0f),
+    (18, 0x01),
+    (19, 0x02),
+    (20, 0x04),
+    (21, 0x06),
+];
+
+fn rgb(red: u8, green: u8, blue: u8) -> ColorRGB {
+    ColorRGB {
+        red: red,
+        green: green,
+        blue: blue,
+    }
+}
+
+fn hex(color: &ColorRGB) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.red, color.green, color.blue)
+}
+
+/// Parse an X11 colour spec the way xterm takes them in OSC 4 and resources do: `#rgb` to
+/// `#rrrrggggbbbb`, which keep the high bits, or `rgb:r/g/b` with 1 to 4 digits each, which
+/// scale.
+pub(crate) fn parse_color(spec: &str) -> Option<ColorRGB> {
+    fn hex_digits(digits: &str) -> Option<u32> {
+        if digits.is_empty() || digits.len() > 4 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        u32::from_str_radix(digits, 16).ok()
+    }
+
+    if let Some(digits) = spec.strip_prefix('#') {
+        let len = digits.len() / 3;
+        if len == 0 || len > 4 || digits.len() != len * 3 {
+            return None;
+        }
+        let channel = |i: usize| {
+            let value = hex_digits(&digits[i * len..(i + 1) * len])?;
+            // Shift the value into the top bits of a 16 bit channel, then keep the top 8
+            Some((value << (16 - 4 * len) >> 8) as u8)
+        };
+        return Some(rgb(channel(0)?, channel(1)?, channel(2)?));
+    }
+
+    let mut channels = spec.strip_prefix("rgb:")?.split('/');
+    let mut channel = || {
+        let digits = channels.next()?;
+        let max = (1u32 << (4 * digits.len())) - 1;
+        let value = hex_digits(digits)?;
+        Some(((value * 255 + max / 2) / max) as u8)
+    };
+    let color = rgb(channel()?, channel()?, channel()?);
+    if channels.next().is_some() {
+        return None;
+    }
+    Some(color)
+}
+
+/// Format a colour the way xterm answers colour queries, with 16 bits a channel
+pub(crate) fn x11_color(color: &ColorRGB) -> String {
+    format!(
+        "rgb:{0:02x}{0:02x}/{1:02x}{1:02x}/{2:02x}{2:02x}",
+        color.red, color.green, color.blue
+    )
+}
+
+/// Handle OSC 4, 10, 11, 104, 110 and 111 given the part after the command number. Queries are
+/// answered in `response`, changes go straight to the state and resets go back to `palette`.
+pub(crate) fn handle_osc_color(
+    state: &mut ffi::VTermState,
+    palette: &Palette,
+    command: u32,
+    data: &[u8],
+    response: &mut Vec<u8>,
+) {
+    let data = String::from_utf8_lossy(data);
+    let mut params = data.split(';');
+
+    match command {
+        4 => {
+            while let (Some(index), Some(spec)) = (params.next(), params.next()) {
+                let index: u8 = match index.parse() {
+                    Ok(index) => index,
+                    Err(_) => continue,
+                };
+                if spec == "?" {
+                    let color = get_rgb_color_from_palette(state, index as usize);
+                    let answer = format!("\x1b]4;{};{}\x1b\\", index, x11_color(&color));
+                    response.extend_from_slice(answer.as_bytes());
+                } else if let Some(color) = parse_color(spec) {
+                    set_palette_color(state, index, &color);
+                }
+            }
+        }
+        104 if data.is_empty() => {
+            for (index, color) in palette.colors.iter().enumerate() {
+                set_palette_color(state, index as u8, color);
+            }
+        }
+        104 => {
+            for index in params.filter_map(|index| index.parse::<u8>().ok()) {
+                set_palette_color(state, index, &palette.colors[index as usize]);
+            }
+        }
+        // Each value after the first moves on to the next dynamic colour, so `OSC 10;?;? ST`
+        // asks for both
+        10 | 11 => {
+            for (command, spec) in (command..12).zip(params) {
+                let (mut fg, mut bg) = get_default_colors(state);
+                let color = if command == 10 { &mut fg } else { &mut bg };
+                if spec == "?" {
+                    let answer = format!("\x1b]{};{}\x1b\\", command, x11_color(color));
+                    response.extend_from_slice(answer.as_bytes());
+                } else if let Some(new_color) = parse_color(spec) {
+                    *color = new_color;
+                    set_default_colors(state, &fg, &bg);
+                }
+            }
+        }
+        110 => {
+            let (_, bg) = get_default_colors(state);
+            set_default_colors(state, &palette.default_fg, &bg);
+        }
+        111 => {
+            let (fg, _) = get_default_colors(state);
+            set_default_colors(state, &fg, &palette.default_bg);
+        }
+        _ => {}
+    }
+}
+
+fn set_palette_color(state: &mut ffi::VTermState, index: u8, color: &ColorRGB) {
+    let color = ffi::VTermColor::from_color_rgb(color);
+    unsafe { ffi::vterm_state_set_palette_color(state, index as c_int, &color) };
+}
+
+fn get_default_colors(state: &ffi::VTermState) -> (ColorRGB, ColorRGB) {
+    let mut fg: ffi::VTermColor = Default::default();
+    let mut bg: ffi::VTermColor = Default::default();
+    unsafe { ffi::vterm_state_get_default_colors(state, &mut fg, &mut bg) };
+    (fg.as_color_rgb(), bg.as_color_rgb())
+}
+
+fn set_default_colors(state: &mut ffi::VTermState, fg: &ColorRGB, bg: &ColorRGB) {
+    let fg = ffi::VTermColor::from_color_rgb(fg);
+    let bg = ffi::VTermColor::from_color_rgb(bg);
+    unsafe { ffi::vterm_state_set_default_colors(state, &fg, &bg) };
+}
+
+impl VTerm {
+    /// Set one palette entry. This is also what OSC 104 resets the entry to.
+    ///
+    /// Cells keep the colour they were written with, so only text written from now on changes.
+    pub fn state_set_palette_color(&mut self, index: u8, color: &ColorRGB) {
+        set_palette_color(unsafe { self.state_ptr.as_mut() }, index, color);
+
+        let data = unsafe { self.callback_data.as_mut() };
+        data.palette.colors[index as usize] = color.clone();
+        data.palette_lookup.invalidate();
+    }
+
+    /// Return the palette as it is now, including changes programs made with OSC 4, 10 and 11
+    pub fn state_get_palette(&self) -> Palette {
+        let state = unsafe { self.state_ptr.as_ref() };
+        let (default_fg, default_bg) = get_default_colors(state);
+
+        Palette {
+            colors: ::std::array::from_fn(|index| get_rgb_color_from_palette(state, index)),
+            default_fg: default_fg,
+            default_bg: default_bg,
+        }
+    }
+
+    /// Replace the whole palette and the default colours in one go, so no input is ever parsed
+    /// with half a theme. This is also what OSC 104, 110 and 111 reset to.
+    pub fn state_set_palette(&mut self, palette: &Palette) {
+        let state = unsafe { self.state_ptr.as_mut() };
+        for (index, color) in palette.colors.iter().enumerate() {
+            set_palette_color(state, index as u8, color);
+        }
+        set_default_colors(state, &palette.default_fg, &palette.default_bg);
+
+        let data = unsafe { self.callback_data.as_mut() };
+        data.palette = palette.clone();
+        data.palette_lookup.invalidate();
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::super::*;
+    use super::*;
+
+    #[test]
+    fn parse_color_handles_x11_specs() {
+        assert_eq!(parse_color("#1a2b3c"), Some(rgb(0x1a, 0x2b, 0x3c)));
+        assert_eq!(parse_color("#abc"), Some(rgb(0xa0, 0xb0, 0xc0)));
+        assert_eq!(parse_color("#1a1b2a2b3a3b"), Some(rgb(0x1a, 0x2a, 0x3a)));
+        assert_eq!(parse_color("rgb:ffff/8080/0000"), Some(rgb(0xff, 0x80, 0)));
+        assert_eq!(parse_color("rgb:f/8/0"), Some(rgb(0xff, 0x88, 0)));
+        assert_eq!(parse_color("rgb:ff/80"), None);
+        assert_eq!(parse_color("#12345"), None);
+        assert_eq!(parse_color("red"), None);
+    }
+
+    #[test]
+    fn palette_from_xresources_reads_colors_over_the_default() {
+        let palette = Palette::from_xresources(
+            "! comment\n#define x y\nURxvt.color1: #ff0000\n*.color255:rgb:10/20/30\n\
+             *background: #000011\nXTerm*scrollBar: false\n",
+        )
+        .unwrap();
+
+        assert_eq!(palette.colors[1], rgb(0xff, 0, 0));
+        assert_eq!(palette.colors[255], rgb(0x10, 0x20, 0x30));
+        assert_eq!(palette.default_bg, rgb(0, 0, 0x11));
+        assert_eq!(palette.colors[2], Palette::default().colors[2]);
+
+        assert_eq!(
+            Palette::from_xresources("*.color0: #000\n*.color1: nope\n"),
+            Err(Error::InvalidTheme { line: 2 })
+        );
+    }
+
+    #[test]
+    fn palette_round_trips_through_xresources() {
+        let mut palette: Palette = Default::default();
+        palette.colors[3] = rgb(1, 2, 3);
+        palette.colors[100] = rgb(4, 5, 6);
+        palette.default_fg = rgb(7, 8, 9);
+
+        let text = palette.to_xresources();
+        assert_eq!(text.lines().count(), 2 + 16 + 1);
+        assert_eq!(Palette::from_xresources(&text), Ok(palette));
+    }
+
+    #[test]
+    fn palette_round_trips_through_base16() {
+        let scheme = "scheme: \"Test\"\nauthor: \"nobody\"\n\
+                      base00: \"000000\"\nbase01: \"010101\"\nbase02: \"020202\"\n\
+                      base03: \"030303\"\nbase04: \"040404\"\nbase05: \"050505\"\n\
+                      base06: \"060606\"\nbase07: \"070707\"\nbase08: \"080808\"\n\
+                      base09: \"090909\"\nbase0A: \"0a0a0a\"\nbase0B: \"0b0b0b\"\n\
+                      base0C: \"0c0c0c\"\nbase0D: \"0d0d0d\"\nbase0E: \"0e0e0e\"\n\
+                      base0F: \"0f0f0f\" # comment\n";
+        let palette = Palette::from_base16(scheme).unwrap();
+
+        assert_eq!(palette.colors[1], rgb(8, 8, 8));
+        assert_eq!(palette.colors[9], rgb(8, 8, 8));
+        assert_eq!(palette.colors[21], rgb(6, 6, 6));
+        assert_eq!(palette.colors[22], Palette::default().colors[22]);
+        assert_eq!(palette.default_fg, rgb(5, 5, 5));
+        assert_eq!(palette.default_bg, rgb(0, 0, 0));
+
+        let text = palette.to_base16("Test");
+        assert!(text.contains("base0A: \"0a0a0a\"\n"));
+        assert_eq!(Palette::from_base16(&text), Ok(palette));
+    }
+}
@@ -0,0 +1,146 @@
+use libc::{c_long, c_void};
+use std::sync::mpsc;
+
+use super::*;
+
+/// The raw pieces libvterm's parser splits input into, before the state layer gives them any
+/// meaning. Byte strings are passed on as they came in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParserEvent {
+    /// A run of printable bytes. Utf8 sequences can be split across two events when they are
+    /// split across two writes.
+    Text(Vec<u8>),
+    /// A C0 or C1 control, including 7 bit C1 sequences like `ESC D`
+    Control(u8),
+    /// An escape sequence's intermediate and final bytes, without the leading ESC
+    Escape(Vec<u8>),
+    Csi {
+        /// Private marker bytes like the `?` in `CSI ? 25 h`. Empty when there were none.
+        leader: Vec<u8>,
+        args: Vec<CsiArg>,
+        intermed: Vec<u8>,
+        command: u8,
+    },
+    /// The OSC string without its terminator
+    Osc(Vec<u8>),
+    /// The DCS string without its terminator
+    Dcs(Vec<u8>),
+    Resize(Size),
+}
+
+/// One CSI argument. libvterm always passes at least one, so `CSI m` has a single missing
+/// argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsiArg {
+    /// None when the argument was left empty
+    pub value: Option<u32>,
+    /// True when the argument was followed by a `:`, which makes the next argument one of its
+    /// sub-parameters, as in `CSI 38:2:255:0:0 m`.
+    pub has_more: bool,
+}
+
+impl CsiArg {
+    pub(crate) fn from_raw(raw: c_long) -> CsiArg {
+        let value = raw & ffi::CSI_ARG_MASK;
+        CsiArg {
+            value: if value == ffi::CSI_ARG_MISSING {
+                None
+            } else {
+                Some(value as u32)
+            },
+            has_more: raw & ffi::CSI_ARG_FLAG_MORE != 0,
+        }
+    }
+
+    /// The value, or the given default when the argument was left empty
+    pub fn value_or(&self, default: u32) -> u32 {
+        self.value.unwrap_or(default)
+    }
+}
+
+impl VTerm {
+    /// Receive the parser's output as ParserEvents on `parser_event_rx`.
+    ///
+    /// This takes the parser away from the state layer, so from then on the state and screen
+    /// don't see any input. It is meant for using libvterm as a tokenizer. Resizes are still
+    /// passed on so the screen's buffers keep matching the terminal size.
+    pub fn parser_receive_events(&mut self) {
+        if self.parser_event_tx.is_none() {
+            let (tx, rx) = mpsc::channel();
+            self.parser_event_tx = Some(tx);
+            self.parser_event_rx = Some(rx);
+        }
+
+        let handler = Box::new(self.parser_event_tx.clone().unwrap());
+        self.set_parser_callbacks(handler);
+    }
+
+    /// Send the parser's output to the given handler instead of a channel. Like
+    /// `parser_receive_events`, this disconnects the state and screen layers.
+    pub fn set_parser_handler(&mut self, handler: Box<dyn ParserHandler>) {
+        // Dropping our sender lets anyone still holding the receiver see the channel close
+        self.parser_event_tx = None;
+        self.set_parser_callbacks(handler);
+    }
+
+    fn set_parser_callbacks(&mut self, handler: Box<dyn ParserHandler>) {
+        let callbacks = ffi::VTermParserCallbacks {
+            // Text has to be registered, because libvterm stops parsing at text nobody ate
+            text: Some(parser_callbacks::text),
+            control: Some(parser_callbacks::control),
+            escape: Some(parser_callbacks::escape),
+            csi: Some(parser_callbacks::csi),
+            osc: Some(parser_callbacks::osc),
+            dcs: Some(parser_callbacks::dcs),
+            resize: Some(parser_callbacks::resize),
+        };
+
+        let data = unsafe { self.callback_data.as_mut() };
+        data.parser_callbacks = Some(callbacks);
+        data.parser_handler = Some(handler);
+
+        unsafe {
+            let data_ptr: *mut c_void = self.callback_data.as_ptr() as *mut c_void;
+            ffi::vterm_parser_set_callbacks(
+                self.ptr.as_mut(),
+                self.callback_data
+                    .as_ref()
+                    .parser_callbacks
+                    .as_ref()
+                    .unwrap(),
+                data_ptr,
+            );
+        }
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::super::*;
+
+    #[test]
+    fn csi_arg_from_raw_handles_missing_and_sub_parameters() {
+        assert_eq!(
+            CsiArg {
+                value: None,
+                has_more: false
+            },
+            CsiArg::from_raw(ffi::CSI_ARG_MISSING)
+        );
+        assert_eq!(
+            CsiArg {
+                value: Some(38),
+                has_more: true
+            },
+            CsiArg::from_raw(38 | ffi::CSI_ARG_FLAG_MORE)
+        );
+        // C sign extends the int flag when it is or'ed into a long
+        assert_eq!(
+            CsiArg {
+                value: Some(2),
+                has_more: true
+            },
+            CsiArg::from_raw(2 | -0x8000_0000)
+        );
+    }
+}
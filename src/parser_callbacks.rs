@@ -0,0 +1,111 @@
+use libc::{c_char, c_int, c_long, c_uchar, c_void, size_t};
+use std::ffi::CStr;
+use std::slice;
+
+use super::*;
+
+pub extern "C" fn text(bytes: *const c_char, len: size_t, data: *mut c_void) -> c_int {
+    let bytes = unsafe { as_bytes(bytes, len) };
+    let is_utf8 = unsafe { int_to_bool(ffi::vterm_get_utf8(cast(data).vterm_ptr.as_ref())) };
+
+    // libvterm hands over everything left in the write and we say how much of it is text. It
+    // only calls us when the first byte is printable.
+    let eaten = bytes
+        .iter()
+        .position(|&b| b < 0x20 || b == 0x7f || (!is_utf8 && (0x80..0xa0).contains(&b)))
+        .unwrap_or(bytes.len())
+        .max(1);
+
+    cast_data(data, |handler| handler.text(&bytes[..eaten]));
+    eaten as c_int
+}
+
+pub extern "C" fn control(control: c_uchar, data: *mut c_void) -> c_int {
+    cast_data(data, |handler| handler.control(control))
+}
+
+pub extern "C" fn escape(bytes: *const c_char, len: size_t, data: *mut c_void) -> c_int {
+    cast_data(data, |handler| {
+        handler.escape(unsafe { as_bytes(bytes, len) })
+    })
+}
+
+pub extern "C" fn csi(
+    leader: *const c_char,
+    args: *const c_long,
+    argcount: c_int,
+    intermed: *const c_char,
+    command: c_char,
+    data: *mut c_void,
+) -> c_int {
+    let args: Vec<CsiArg> = unsafe { slice::from_raw_parts(args, argcount as usize) }
+        .iter()
+        .map(|&arg| CsiArg::from_raw(arg))
+        .collect();
+
+    cast_data(data, |handler| {
+        handler.csi(
+            unsafe { c_str_bytes(leader) },
+            &args,
+            unsafe { c_str_bytes(intermed) },
+            command as u8,
+        )
+    })
+}
+
+pub extern "C" fn osc(command: *const c_char, cmdlen: size_t, data: *mut c_void) -> c_int {
+    cast_data(data, |handler| {
+        handler.osc(unsafe { as_bytes(command, cmdlen) })
+    })
+}
+
+pub extern "C" fn dcs(command: *const c_char, cmdlen: size_t, data: *mut c_void) -> c_int {
+    cast_data(data, |handler| {
+        handler.dcs(unsafe { as_bytes(command, cmdlen) })
+    })
+}
+
+pub extern "C" fn resize(rows: c_int, cols: c_int, data: *mut c_void) -> c_int {
+    // The screen still needs to resize its buffers, so pass this on to the state layer
+    let layer = cast(data).layer_parser;
+    if let Some(layer_resize) = unsafe { layer.0.as_ref() }.and_then(|callbacks| callbacks.resize) {
+        layer_resize(rows, cols, layer.1);
+    }
+
+    cast_data(data, |handler| {
+        handler.resize(Size::new(cols as usize, rows as usize))
+    })
+}
+
+pub(crate) unsafe fn as_bytes<'a>(ptr: *const c_char, len: size_t) -> &'a [u8] {
+    if ptr.is_null() || len == 0 {
+        return &[];
+    }
+    slice::from_raw_parts(ptr as *const u8, len)
+}
+
+/// libvterm passes a null pointer for an empty leader or intermediate string
+pub(crate) unsafe fn c_str_bytes<'a>(ptr: *const c_char) -> &'a [u8] {
+    if ptr.is_null() {
+        return &[];
+    }
+    CStr::from_ptr(ptr).to_bytes()
+}
+
+fn cast<'a>(data: *mut c_void) -> &'a mut CallbackData {
+    unsafe { &mut *(data as *mut CallbackData) }
+}
+
+/// Call the given closure with the parser handler, if there is one.
+fn cast_data<F>(data: *mut c_void, closure: F) -> c_int
+where
+    F: FnOnce(&mut dyn ParserHandler),
+{
+    match cast(data).parser_handler.as_mut() {
+        Some(handler) => {
+            closure(handler.as_mut());
+            1
+        }
+        None => 0,
+    }
+}
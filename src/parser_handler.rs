@@ -0,0 +1,62 @@
+#![allow(unused_variables)]
+
+use std::sync::mpsc;
+
+use super::*;
+
+/// Receives the parser's output as it happens. Every method has a no-op default, so implement
+/// only the ones you care about.
+///
+/// Register one with `VTerm::set_parser_handler`. The parser only has one set of callbacks, so a
+/// parser handler takes the place of the state layer and the state and screen stop seeing input.
+pub trait ParserHandler {
+    fn text(&mut self, bytes: &[u8]) {}
+
+    fn control(&mut self, control: u8) {}
+
+    fn escape(&mut self, bytes: &[u8]) {}
+
+    fn csi(&mut self, leader: &[u8], args: &[CsiArg], intermed: &[u8], command: u8) {}
+
+    fn osc(&mut self, command: &[u8]) {}
+
+    fn dcs(&mut self, command: &[u8]) {}
+
+    fn resize(&mut self, size: Size) {}
+}
+
+/// The channel api. Each callback is turned into a ParserEvent and sent.
+impl ParserHandler for mpsc::Sender<ParserEvent> {
+    fn text(&mut self, bytes: &[u8]) {
+        let _ = self.send(ParserEvent::Text(bytes.to_vec()));
+    }
+
+    fn control(&mut self, control: u8) {
+        let _ = self.send(ParserEvent::Control(control));
+    }
+
+    fn escape(&mut self, bytes: &[u8]) {
+        let _ = self.send(ParserEvent::Escape(bytes.to_vec()));
+    }
+
+    fn csi(&mut self, leader: &[u8], args: &[CsiArg], intermed: &[u8], command: u8) {
+        let _ = self.send(ParserEvent::Csi {
+            leader: leader.to_vec(),
+            args: args.to_vec(),
+            intermed: intermed.to_vec(),
+            command: command,
+        });
+    }
+
+    fn osc(&mut self, command: &[u8]) {
+        let _ = self.send(ParserEvent::Osc(command.to_vec()));
+    }
+
+    fn dcs(&mut self, command: &[u8]) {
+        let _ = self.send(ParserEvent::Dcs(command.to_vec()));
+    }
+
+    fn resize(&mut self, size: Size) {
+        let _ = self.send(ParserEvent::Resize(size));
+    }
+}
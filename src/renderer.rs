@@ -0,0 +1,461 @@
+use std::io::{self, Write};
+
+use super::*;
+
+/// Draws snapshots of a VTerm's screen onto a real terminal, sending only what changed since the
+/// last draw: scrolls, the cells that differ, SGR changes and cursor moves. Draw a new snapshot
+/// whenever the screen reports Damage, MoveRect or MoveCursor events, or simply after each write.
+///
+/// The renderer remembers what the host shows and where it left the host's cursor and pen, so
+/// call `invalidate` after writing anything else to the host. The title and alt screen are left
+/// alone, since they belong to the host's whole window.
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    /// Where the screen's top left cell goes on the host, for drawing inside a larger screen
+    pub offset: Pos,
+    /// Whether rows can be moved by scrolling the host. Scroll regions span the host's whole
+    /// width, so this is only safe when the screen does too. Otherwise moved rows are redrawn.
+    pub host_scrolling: bool,
+
+    shown: Option<Snapshot>,
+    /// Where the host's cursor is, relative to the offset, when we know
+    cursor: Option<Pos>,
+    cursor_visible: Option<bool>,
+    cursor_shape: Option<CursorShape>,
+    pen: Option<Pen>,
+    /// Draw blank cells by erasing them with ECH rather than writing spaces, so a host that is
+    /// itself a VTerm ends up with the same cells
+    pub(crate) erase_blanks: bool,
+}
+
+/// What SGR and OSC 8 set, which is everything about a cell but its text
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Pen {
+    attrs: ScreenCellAttr,
+    fg: Color,
+    bg: Color,
+    hyperlink: Option<Hyperlink>,
+}
+
+impl Pen {
+    pub(crate) fn from_cell(cell: &ScreenCell) -> Pen {
+        Pen {
+            // Line attributes aren't set through SGR
+            attrs: ScreenCellAttr {
+                dwl: false,
+                dhl: 0,
+                ..cell.attrs.clone()
+            },
+            fg: cell.fg.clone(),
+            bg: cell.bg.clone(),
+            hyperlink: cell.hyperlink.clone(),
+        }
+    }
+}
+
+impl Default for Renderer {
+    fn default() -> Renderer {
+        Renderer::new()
+    }
+}
+
+impl Renderer {
+    /// A renderer for a screen that fills the host
+    pub fn new() -> Renderer {
+        Renderer {
+            offset: Pos::new(0, 0),
+            host_scrolling: true,
+            shown: None,
+            cursor: None,
+            cursor_visible: None,
+            cursor_shape: None,
+            pen: None,
+            erase_blanks: false,
+        }
+    }
+
+    /// A renderer for a screen drawn at `offset` inside a larger host, which redraws moved rows
+    /// rather than scrolling the host
+    pub fn with_offset(offset: Pos) -> Renderer {
+        Renderer {
+            offset: offset,
+            host_scrolling: false,
+            ..Renderer::new()
+        }
+    }
+
+    /// Forget what the host shows, so the next draw repaints everything
+    pub fn invalidate(&mut self) {
+        self.shown = None;
+        self.cursor = None;
+        self.cursor_visible = None;
+        self.cursor_shape = None;
+        self.pen = None;
+    }
+
+    /// Bring the host up to date with the snapshot. The output is written in one go and not
+    /// flushed.
+    pub fn draw(&mut self, snapshot: &Snapshot, out: &mut dyn Write) -> io::Result<()> {
+        let mut buf: Vec<u8> = vec![];
+        let width = snapshot.size.width;
+
+        // What the host shows, kept up to date as changes are drawn so unchanged cells can be
+        // skipped. None when it isn't known and everything has to be painted.
+        let (mut shown, changes) = match self.shown.take() {
+            Some(mut shown) if shown.size == snapshot.size => {
+                let changes = if self.host_scrolling {
+                    self.scroll_host(&mut shown, snapshot, &mut buf)
+                } else {
+                    shown.diff(snapshot)
+                };
+                (Some(shown), changes)
+            }
+            _ => {
+                let changes = snapshot
+                    .rows()
+                    .enumerate()
+                    .map(|(y, row)| Change::Row {
+                        y: y,
+                        cells: row.to_vec(),
+                    })
+                    .collect();
+                (None, changes)
+            }
+        };
+
+        for change in &changes {
+            let (start, len) = match *change {
+                // Without host scrolling, moved cells are drawn where they land
+                Change::MoveRect { ref dest, .. } => {
+                    for y in dest.top()..dest.bottom() {
+                        let start = Pos::new(dest.left(), y);
+                        self.draw_cells(snapshot, start, dest.size.width, shown.as_mut(), &mut buf);
+                    }
+                    continue;
+                }
+                Change::Row { y, .. } => (Pos::new(0, y), width),
+                Change::Text { pos, ref cells } | Change::Attrs { pos, ref cells } => {
+                    (pos, cells.len())
+                }
+                _ => continue,
+            };
+            self.draw_cells(snapshot, start, len, shown.as_mut(), &mut buf);
+        }
+
+        if snapshot.cursor_visible {
+            self.move_cursor(snapshot.cursor_pos, &mut buf);
+            if self.cursor_shape.as_ref() != Some(&snapshot.cursor_shape) {
+                let decscusr = match snapshot.cursor_shape {
+                    CursorShape::Block => 2,
+                    CursorShape::Underline => 4,
+                    CursorShape::BarLeft => 6,
+                };
+                write!(buf, "\x1b[{} q", decscusr)?;
+                self.cursor_shape = Some(snapshot.cursor_shape.clone());
+            }
+        }
+        if self.cursor_visible != Some(snapshot.cursor_visible) {
+            buf.extend_from_slice(if snapshot.cursor_visible {
+                b"\x1b[?25h"
+            } else {
+                b"\x1b[?25l"
+            });
+            self.cursor_visible = Some(snapshot.cursor_visible);
+        }
+
+        self.shown = Some(snapshot.clone());
+        out.write_all(&buf)
+    }
+
+    /// Do the scrolls in `shown.diff(snapshot)` on the host and return the changes left after
+    /// them. The host blanks the rows a scroll uncovers, where a MoveRect leaves them alone, so
+    /// the diff is taken again after each one.
+    fn scroll_host(
+        &mut self,
+        shown: &mut Snapshot,
+        snapshot: &Snapshot,
+        buf: &mut Vec<u8>,
+    ) -> Vec<Change> {
+        let mut changes = shown.diff(snapshot);
+
+        // Each scroll fixes rows, so this ends well before the limit
+        for _ in 0..snapshot.size.height {
+            let (dest, src) = match changes.first() {
+                Some(&Change::MoveRect { dest, src }) => (dest, src),
+                _ => break,
+            };
+
+            // The host fills uncovered rows with the current background
+            self.set_pen(&Default::default(), buf);
+
+            let top = dest.top().min(src.top());
+            let bottom = dest.bottom().max(src.bottom());
+            let _ = write!(
+                buf,
+                "\x1b[{};{}r",
+                self.offset.y + top + 1,
+                self.offset.y + bottom
+            );
+            let uncovered = if src.top() > dest.top() {
+                let _ = write!(buf, "\x1b[{}S", src.top() - dest.top());
+                dest.bottom()..bottom
+            } else {
+                let _ = write!(buf, "\x1b[{}T", dest.top() - src.top());
+                top..dest.top()
+            };
+            // Setting or clearing the region homes the cursor
+            buf.extend_from_slice(b"\x1b[r");
+            self.cursor = None;
+
+            shown.apply(&changes[..1]);
+            let width = shown.size.width;
+            for cell in &mut shown.cells[uncovered.start * width..uncovered.end * width] {
+                *cell = Default::default();
+            }
+
+            changes = shown.diff(snapshot);
+        }
+
+        changes
+    }
+
+    /// Draw a run of the snapshot's cells along one row, skipping those the host already shows.
+    /// Right halves of wide characters are skipped too, since drawing the left half covers them.
+    fn draw_cells(
+        &mut self,
+        snapshot: &Snapshot,
+        start: Pos,
+        len: usize,
+        mut shown: Option<&mut Snapshot>,
+        buf: &mut Vec<u8>,
+    ) {
+        let width = snapshot.size.width;
+        // libvterm works out a cell's width from the cell after it, so overwriting half of a wide
+        // character leaves cells whose width doesn't match their character. They're drawn as the
+        // host will show them, which covers the next cell or leaves it showing, so that cell is
+        // drawn again too. A right half that nothing covers is drawn as a space.
+        let mut redraw_next = false;
+        let end = (start.x + len + 1).min(width);
+        let mut x = start.x;
+        while x < end {
+            let index = start.y * width + x;
+            let cell = &snapshot.cells[index];
+            let redraw = redraw_next;
+            redraw_next = false;
+            if x == start.x + len && !redraw {
+                break;
+            }
+            if let Some(ref mut shown) = shown {
+                if looks_same(&shown.cells[index], cell) && !redraw {
+                    x += 1;
+                    continue;
+                }
+                shown.cells[index] = cell.clone();
+            }
+
+            let covered = x > 0 && drawn_width(&snapshot.cells[index - 1]) == 2;
+            if cell.width == 0 && covered {
+                x += 1;
+                continue;
+            }
+
+            self.move_cursor(Pos::new(x, start.y), buf);
+            let pen = Pen::from_cell(cell);
+            self.set_pen(&pen, buf);
+
+            if self.erase_blanks && cell.chars.is_empty() && cell.width == 1 {
+                // Erase the whole run of blanks with this pen, which leaves the cursor where it is
+                let mut count = 1;
+                while x + count < start.x + len {
+                    let next = &snapshot.cells[index + count];
+                    if !next.chars.is_empty() || next.width != 1 || Pen::from_cell(next) != pen {
+                        break;
+                    }
+                    if let Some(ref mut shown) = shown {
+                        shown.cells[index + count] = next.clone();
+                    }
+                    count += 1;
+                }
+                if count == 1 {
+                    buf.extend_from_slice(b"\x1b[X");
+                } else {
+                    let _ = write!(buf, "\x1b[{}X", count);
+                }
+                x += count;
+                continue;
+            }
+
+            let drawn = if cell.width == 0 {
+                1
+            } else {
+                drawn_width(cell)
+            };
+            redraw_next = cell.width != 0 && drawn != cell.width as usize;
+            if cell.width == 0 || x + drawn > width {
+                buf.push(b' ');
+            } else {
+                // A combining character can end up alone in a cell, but on the host it would
+                // join whatever was drawn before it, so give it a space to sit on
+                if cell.chars.first().map_or(false, |&c| is_combining(c)) {
+                    buf.push(b' ');
+                }
+                let _ = write!(buf, "{}", cell);
+            }
+
+            // At the last column the host may be waiting to wrap, so don't guess
+            let next = x + drawn;
+            self.cursor = if next < width {
+                Some(Pos::new(next, start.y))
+            } else {
+                None
+            };
+            x += 1;
+        }
+    }
+
+    /// Move the host's cursor with whichever sequence is shortest
+    fn move_cursor(&mut self, to: Pos, buf: &mut Vec<u8>) {
+        if self.cursor == Some(to) {
+            return;
+        }
+
+        let host = Pos::new(self.offset.x + to.x, self.offset.y + to.y);
+        let mut best = if host == Pos::new(0, 0) {
+            "\x1b[H".to_string()
+        } else {
+            format!("\x1b[{};{}H", host.y + 1, host.x + 1)
+        };
+
+        if let Some(from) = self.cursor {
+            let mut candidates: Vec<String> = vec![];
+            if from.y == to.y {
+                if host.x == 0 {
+                    candidates.push("\r".to_string());
+                }
+                if to.x > from.x {
+                    candidates.push(format!("\x1b[{}C", to.x - from.x));
+                } else {
+                    candidates.push(format!("\x1b[{}D", from.x - to.x));
+                }
+            } else if from.x == to.x {
+                if to.y > from.y {
+                    candidates.push(format!("\x1b[{}B", to.y - from.y));
+                } else {
+                    candidates.push(format!("\x1b[{}A", from.y - to.y));
+                }
+            }
+
+            for candidate in candidates {
+                if candidate.len() < best.len() {
+                    best = candidate;
+                }
+            }
+        }
+
+        buf.extend_from_slice(best.as_bytes());
+        self.cursor = Some(to);
+    }
+
+    /// Change the host's pen with the fewest SGR parameters, starting from a reset when it isn't
+    /// known
+    pub(crate) fn set_pen(&mut self, pen: &Pen, buf: &mut Vec<u8>) {
+        if self.pen.as_ref() == Some(pen) {
+            return;
+        }
+
+        let mut params: Vec<String> = vec![];
+        let from = match self.pen.take() {
+            Some(from) => from,
+            None => {
+                params.push("0".to_string());
+                Default::default()
+            }
+        };
+
+        let mut toggle = |was: bool, is: bool, on: &str, off: &str| {
+            if was != is {
+                params.push(if is { on } else { off }.to_string());
+            }
+        };
+        toggle(from.attrs.bold, pen.attrs.bold, "1", "22");
+        toggle(from.attrs.italic, pen.attrs.italic, "3", "23");
+        toggle(from.attrs.blink, pen.attrs.blink, "5", "25");
+        toggle(from.attrs.reverse, pen.attrs.reverse, "7", "27");
+        toggle(from.attrs.strike, pen.attrs.strike, "9", "29");
+
+        if from.attrs.underline != pen.attrs.underline {
+            params.push(
+                match pen.attrs.underline {
+                    0 => "24",
+                    1 => "4",
+                    2 => "21",
+                    _ => "4:3",
+                }
+                .to_string(),
+            );
+        }
+        if from.attrs.font != pen.attrs.font {
+            params.push((10 + pen.attrs.font as usize).to_string());
+        }
+        if from.fg != pen.fg {
+            params.push(color_sgr(&pen.fg, 30, 90));
+        }
+        if from.bg != pen.bg {
+            params.push(color_sgr(&pen.bg, 40, 100));
+        }
+
+        if !params.is_empty() {
+            let _ = write!(buf, "\x1b[{}m", params.join(";"));
+        }
+
+        // A reset doesn't close a hyperlink, so an unknown pen might still have one open
+        if params.first().map(|p| p.as_str()) == Some("0") || from.hyperlink != pen.hyperlink {
+            match pen.hyperlink {
+                Some(ref link) => {
+                    let id = link
+                        .id
+                        .as_ref()
+                        .map(|id| format!("id={}", id))
+                        .unwrap_or_default();
+                    let _ = write!(buf, "\x1b]8;{};{}\x1b\\", id, link.uri);
+                }
+                None => buf.extend_from_slice(b"\x1b]8;;\x1b\\"),
+            }
+        }
+
+        self.pen = Some(pen.clone());
+    }
+}
+
+/// Whether drawing one cell over the other would change nothing on the host. The RGB values and
+/// palette indexes libvterm resolves colours to aren't drawn, so they're ignored.
+fn looks_same(a: &ScreenCell, b: &ScreenCell) -> bool {
+    a.chars == b.chars && a.width == b.width && Pen::from_cell(a) == Pen::from_cell(b)
+}
+
+/// How many columns the host moves across to draw a cell, which is the width of its character
+/// rather than the width libvterm gives the cell
+fn drawn_width(cell: &ScreenCell) -> usize {
+    match cell.chars.first() {
+        Some(&c) if cell.width > 0 && !is_combining(c) => {
+            unsafe { ffi::vterm_unicode_width(c as i32) }.clamp(1, 2) as usize
+        }
+        _ => 1,
+    }
+}
+
+fn is_combining(c: char) -> bool {
+    int_to_bool(unsafe { ffi::vterm_unicode_is_combining(c as i32) })
+}
+
+/// The SGR parameters for a colour. `base` is 30 for the foreground and 40 for the background,
+/// and `bright_base` is where the bright colours start.
+fn color_sgr(color: &Color, base: usize, bright_base: usize) -> String {
+    match *color {
+        Color::Default => (base + 9).to_string(),
+        Color::Indexed(index) if index < 8 => (base + index as usize).to_string(),
+        Color::Indexed(index) if index < 16 => (bright_base + index as usize - 8).to_string(),
+        Color::Indexed(index) => format!("{};5;{}", base + 8, index),
+        Color::Rgb(ref rgb) => format!("{};2;{};{};{}", base + 8, rgb.red, rgb.green, rgb.blue),
+    }
+}
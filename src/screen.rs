@@ -75,17 +75,22 @@ impl VTerm {
         unsafe { ffi::vterm_screen_reset(self.screen_ptr.as_mut(), super::bool_to_int(is_hard)) }
     }
 
-    /// Return the cell at the given position
+    /// Return the cell at the given position. Panics if the position is off the screen, see
+    /// `try_screen_get_cell` for a version that doesn't.
     pub fn screen_get_cell(&self, pos: &Pos) -> ScreenCell {
-        let size = self.get_size();
-        if pos.x >= size.width || pos.y >= size.height {
-            panic!(
-                "given position out of bounds: size={:?} pos={:?}",
-                size, pos
-            );
-        }
+        self.try_screen_get_cell(pos)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Return the cell at the given position, or an error if it's off the screen
+    pub fn try_screen_get_cell(&self, pos: &Pos) -> Result<ScreenCell, Error> {
+        check_pos(self.get_size(), pos)?;
 
         let cell_buf = unsafe { ffi::vterm_cell_new() };
+        if cell_buf.is_null() {
+            return Err(Error::AllocationFailed);
+        }
+
         unsafe {
             ffi::vterm_screen_get_cell(
                 self.screen_ptr.as_ref(),
@@ -93,35 +98,35 @@ impl VTerm {
                 cell_buf,
             )
         };
-        let cell = ScreenCell::from_ptr(cell_buf, &self); // shouldn't this take &cell_buf?
+        let cell = ScreenCell::from_ptr(cell_buf, self); // shouldn't this take &cell_buf?
         unsafe { ffi::vterm_cell_free(cell_buf) };
 
-        cell
+        Ok(cell)
     }
 
-    // Returns the text within the rect as a String. Invalid utf8 sequences are replaces with or
-    // panics if invalid utf8 bytes are found
+    // Returns the text within the rect as a String. Invalid utf8 sequences are replaced with
+    // U+FFFD. Panics if the rect is off the screen, see `try_screen_get_text_lossy`.
     pub fn screen_get_text_lossy(&self, rect: &Rect) -> String {
-        let bytes = self.get_text_as_bytes(rect);
-        String::from_utf8_lossy(&bytes).into_owned()
+        self.try_screen_get_text_lossy(rect)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `screen_get_text_lossy` but returns an error if the rect is off the screen
+    pub fn try_screen_get_text_lossy(&self, rect: &Rect) -> Result<String, Error> {
+        let bytes = self.get_text_as_bytes(rect)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
     }
 
-    // Returns the text within the rect as a String or panics if invalid utf8 bytes are found
-    pub fn screen_get_text(&self, rect: &Rect) -> Result<String, ::std::string::FromUtf8Error> {
-        let bytes = self.get_text_as_bytes(rect);
+    /// Returns the text within the rect as a String, or an error if the rect is off the screen or
+    /// the text isn't valid utf8
+    pub fn screen_get_text(&self, rect: &Rect) -> Result<String, Error> {
+        let bytes = self.get_text_as_bytes(rect)?;
         let v = String::from_utf8(bytes)?;
         Ok(v)
     }
 
-    fn get_text_as_bytes(&self, rect: &Rect) -> Vec<u8> {
-        let screen_rect = Rect::new(Pos::new(0, 0), self.get_size());
-        if !screen_rect.contains_rect(&rect) {
-            panic!(
-                "given rect out of bounds: size={:?} rect={:?}",
-                self.get_size(),
-                rect
-            );
-        }
+    fn get_text_as_bytes(&self, rect: &Rect) -> Result<Vec<u8>, Error> {
+        check_rect(self.get_size(), rect)?;
 
         let size: usize = rect.size.width * rect.size.height * ffi::VTERM_MAX_CHARS_PER_CELL;
         let mut bytes = Vec::with_capacity(size);
@@ -138,7 +143,15 @@ impl VTerm {
             bytes.set_len(len);
         }
 
-        bytes.into_iter().map(|c| c as u8).collect()
+        Ok(bytes.into_iter().map(|c| c as u8).collect())
+    }
+
+    /// Allow the program to switch to the alternate screen buffer. Without this, requests to
+    /// turn the alt screen on are ignored.
+    pub fn screen_enable_altscreen(&mut self, is_enabled: bool) {
+        unsafe {
+            ffi::vterm_screen_enable_altscreen(self.screen_ptr.as_mut(), bool_to_int(is_enabled))
+        };
     }
 
     pub fn screen_flush_damage(&mut self) {
@@ -155,14 +168,40 @@ impl VTerm {
         unsafe { ffi::vterm_screen_set_damage_merge(self.screen_ptr.as_mut(), ffi_size) };
     }
 
+    /// Return the cells in the rect, row by row. Panics if the rect is off the screen, see
+    /// `try_screen_get_cells_in_rect`.
     pub fn screen_get_cells_in_rect(&self, rect: &Rect) -> Vec<ScreenCell> {
-        let mut cells: Vec<ScreenCell> = Vec::new(); // capacity is known here FYI
+        self.try_screen_get_cells_in_rect(rect)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `screen_get_cells_in_rect` but returns an error if the rect is off the screen
+    pub fn try_screen_get_cells_in_rect(&self, rect: &Rect) -> Result<Vec<ScreenCell>, Error> {
+        check_rect(self.get_size(), rect)?;
+        Ok(self.get_cells_in_rect(rect))
+    }
 
-        for pos in rect.positions() {
-            cells.push(self.screen_get_cell(&pos));
+    /// Read the cells in a rect that's known to be on the screen, crossing into C once for the
+    /// whole rect rather than once per cell
+    pub(crate) fn get_cells_in_rect(&self, rect: &Rect) -> Vec<ScreenCell> {
+        let len = rect.size.width * rect.size.height;
+        let mut flat_cells: Vec<ffi::VTermFlatCell> = Vec::with_capacity(len);
+        unsafe {
+            let count = ffi::vterm_screen_get_flat_cells(
+                self.screen_ptr.as_ref(),
+                ffi::VTermRect::from_rect(rect),
+                flat_cells.as_mut_ptr(),
+                len as size_t,
+            );
+            flat_cells.set_len(count as usize);
         }
 
-        cells
+        let state = unsafe { self.state_ptr.as_ref() };
+        let data = unsafe { self.callback_data.as_ref() };
+        flat_cells
+            .iter()
+            .map(|flat| ScreenCell::from_flat(flat, state, &data.palette_lookup, &data.hyperlinks))
+            .collect()
     }
 
     /// calling this method will setup the vterm to generate ScreenEvent messages to a channel. The
@@ -212,20 +251,66 @@ impl VTerm {
             None
         };
 
-        self.screen_callbacks = Some(callbacks);
-
         if self.screen_event_tx.is_none() {
             let (tx, rx) = mpsc::channel();
             self.screen_event_tx = Some(tx);
             self.screen_event_rx = Some(rx);
         }
 
+        let handler = Box::new(self.screen_event_tx.clone().unwrap());
+        self.set_screen_callbacks(callbacks, handler);
+    }
+
+    /// Send every screen callback to the given handler instead of a channel. The handler is
+    /// called synchronously from `write`, and what it returns goes straight back to libvterm.
+    pub fn set_screen_handler(&mut self, handler: Box<dyn ScreenHandler>) {
+        let callbacks = ffi::VTermScreenCallbacks {
+            damage: Some(screen_callbacks::damage),
+            move_rect: Some(screen_callbacks::move_rect),
+            move_cursor: Some(screen_callbacks::move_cursor),
+            set_term_prop: Some(screen_callbacks::set_term_prop),
+            bell: Some(screen_callbacks::bell),
+            resize: Some(screen_callbacks::resize),
+            sb_pushline: Some(screen_callbacks::sb_pushline),
+            sb_popline: Some(screen_callbacks::sb_popline),
+        };
+
+        // Dropping our sender lets anyone still holding the receiver see the channel close
+        self.screen_event_tx = None;
+        self.set_screen_callbacks(callbacks, handler);
+    }
+
+    fn set_screen_callbacks(
+        &mut self,
+        callbacks: ffi::VTermScreenCallbacks,
+        handler: Box<dyn ScreenHandler>,
+    ) {
+        let data = unsafe { self.callback_data.as_mut() };
+        data.screen_callbacks = Some(callbacks);
+        data.screen_handler = Some(handler);
+
+        self.register_screen_callbacks();
+    }
+
+    /// Give libvterm the handler's callbacks, plus the scrollback ones if scrollback is enabled
+    /// and set_term_prop, which keeps track of the title
+    pub(crate) fn register_screen_callbacks(&mut self) {
+        let data = unsafe { self.callback_data.as_mut() };
+
+        let mut callbacks = data.screen_callbacks.unwrap_or_default();
+        callbacks.set_term_prop = Some(screen_callbacks::set_term_prop);
+        if data.scrollback.is_some() {
+            callbacks.sb_pushline = Some(screen_callbacks::sb_pushline);
+            callbacks.sb_popline = Some(screen_callbacks::sb_popline);
+        }
+        data.registered_screen_callbacks = callbacks;
+
         unsafe {
-            let self_ptr: *mut c_void = self as *mut _ as *mut c_void;
+            let data_ptr: *mut c_void = self.callback_data.as_ptr() as *mut c_void;
             ffi::vterm_screen_set_callbacks(
                 self.screen_ptr.as_mut(),
-                self.screen_callbacks.as_ref().unwrap(),
-                self_ptr,
+                &self.callback_data.as_ref().registered_screen_callbacks,
+                data_ptr,
             );
         }
     }
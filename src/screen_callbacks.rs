@@ -2,172 +2,170 @@ use libc::{c_int, c_void};
 
 use super::*;
 
-pub extern "C" fn damage(rect: ffi::VTermRect, vterm: *mut c_void) -> c_int {
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => {
-            match tx.send(ScreenEvent::Damage(DamageEvent {
-                rect: rect.as_rect(),
-            })) {
-                Ok(_) => 1,
-                Err(_) => 0,
-            }
-        }
-        None => 0,
-    }
+pub extern "C" fn damage(rect: ffi::VTermRect, data: *mut c_void) -> c_int {
+    cast_data(data, |_, handler| {
+        handler.damage(rect.as_rect());
+        1
+    })
 }
 
-pub extern "C" fn move_rect(
-    dest: ffi::VTermRect,
-    src: ffi::VTermRect,
-    vterm: *mut c_void,
-) -> c_int {
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => {
-            match tx.send(ScreenEvent::MoveRect(MoveRectEvent {
-                dest: dest.as_rect(),
-                src: src.as_rect(),
-            })) {
-                Ok(_) => 1,
-                Err(_) => 0,
-            }
-        }
-        None => 0,
-    }
+pub extern "C" fn move_rect(dest: ffi::VTermRect, src: ffi::VTermRect, data: *mut c_void) -> c_int {
+    cast_data(data, |_, handler| {
+        bool_to_int(handler.move_rect(dest.as_rect(), src.as_rect()))
+    })
 }
 
 pub extern "C" fn move_cursor(
     new: ffi::VTermPos,
     old: ffi::VTermPos,
     is_visible: c_int,
-    vterm: *mut c_void,
+    data: *mut c_void,
 ) -> c_int {
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => {
-            let event = ScreenEvent::MoveCursor(MoveCursorEvent {
-                new: new.as_pos(),
-                old: old.as_pos(),
-                is_visible: super::int_to_bool(is_visible),
-            });
-            match tx.send(event) {
-                Ok(_) => 1,
-                Err(_) => 0,
-            }
-        }
-        None => 0,
-    }
+    cast_data(data, |_, handler| {
+        handler.move_cursor(new.as_pos(), old.as_pos(), int_to_bool(is_visible));
+        1
+    })
 }
 
 pub extern "C" fn set_term_prop(
     prop: ffi::VTermProp,
     val: *mut ffi::VTermValue,
-    vterm: *mut c_void,
+    data: *mut c_void,
 ) -> c_int {
-    let event: ScreenEvent = match prop {
-        ffi::VTermProp::VTermPropAltscreen => {
-            let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
-            ScreenEvent::AltScreen(AltScreenEvent { is_on: val })
-        }
-        ffi::VTermProp::VTermPropCursorBlink => {
-            let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
-            ScreenEvent::CursorBlink(CursorBlinkEvent { is_on: val })
-        }
-        ffi::VTermProp::VTermPropCursorShape => ScreenEvent::CursorShape(CursorShapeEvent {
-            shape: CursorShape::Block,
-        }),
-        ffi::VTermProp::VTermPropCursorVisible => {
-            ScreenEvent::CursorVisible(CursorVisibleEvent { is_on: true })
-        }
-        ffi::VTermProp::VTermPropIconName => ScreenEvent::IconName(IconNameEvent {
-            name: "fake icon name".to_string(),
-        }),
-        ffi::VTermProp::VTermPropMouse => ScreenEvent::Mouse(MouseEvent {
-            mode: MouseMode::None,
-        }),
-        ffi::VTermProp::VTermPropReverse => ScreenEvent::Reverse(ReverseEvent { is_on: true }),
-        ffi::VTermProp::VTermPropTitle => ScreenEvent::Title(TitleEvent {
-            title: "fake title".to_string(),
-        }),
+    let data: &mut CallbackData = unsafe { &mut *(data as *mut CallbackData) };
+    let prop = match TermProp::from_ffi(prop, val) {
+        Some(prop) => prop,
+        None => return 1,
+    };
+    let title = match prop {
+        TermProp::Title(ref title) => Some(title.clone()),
+        _ => None,
     };
 
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => match tx.send(event) {
-            Ok(_) => 1,
-            Err(_) => 0,
-        },
-        None => 0,
+    let is_accepted = match wanting_handler(
+        &data.screen_callbacks,
+        &mut data.screen_handler,
+        |callbacks| callbacks.set_term_prop.is_some(),
+    ) {
+        Some(handler) => handler.set_term_prop(prop),
+        None => true,
+    };
+
+    // libvterm doesn't keep the title, so we do for snapshots
+    if let (true, Some(title)) = (is_accepted, title) {
+        data.title = title;
     }
+
+    bool_to_int(is_accepted)
 }
 
-pub extern "C" fn bell(vterm: *mut c_void) -> c_int {
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => match tx.send(ScreenEvent::Bell) {
-            Ok(_) => 1,
-            Err(_) => 0,
-        },
-        None => 0,
-    }
+pub extern "C" fn bell(data: *mut c_void) -> c_int {
+    cast_data(data, |_, handler| {
+        handler.bell();
+        1
+    })
 }
-pub extern "C" fn resize(rows: c_int, cols: c_int, vterm: *mut c_void) -> c_int {
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => {
-            match tx.send(ScreenEvent::Resize(ResizeEvent {
-                size: Size::new(cols as usize, rows as usize),
-            })) {
-                Ok(_) => 1,
-                Err(_) => 0,
-            }
-        }
-        None => 0,
-    }
+
+pub extern "C" fn resize(rows: c_int, cols: c_int, data: *mut c_void) -> c_int {
+    cast_data(data, |_, handler| {
+        handler.resize(Size::new(cols as usize, rows as usize));
+        1
+    })
 }
+
 pub extern "C" fn sb_pushline(
     cols: c_int,
     cells_ptr: *const ffi::VTermScreenCell,
-    vterm: *mut c_void,
+    data: *mut c_void,
 ) -> c_int {
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => {
-            let mut cells = vec![];
-            for i in 0..(cols as usize) {
-                let ptr = unsafe { ffi::vterm_cell_pointer_arithmetic(cells_ptr, i as c_int) };
-                cells.push(ScreenCell::from_ptr(ptr, &vterm));
-            }
-
-            match tx.send(ScreenEvent::SbPushLine(SbPushLineEvent { cells: cells })) {
-                Ok(_) => 1,
-                Err(_) => 0,
-            }
-        }
-        None => 0,
+    let data: &mut CallbackData = unsafe { &mut *(data as *mut CallbackData) };
+    let line = ScreenLine::new(
+        cells_ptr,
+        cols as usize,
+        unsafe { data.state_ptr.as_ref() },
+        &data.palette_lookup,
+        &data.hyperlinks,
+    );
+
+    if let Some(scrollback) = data.scrollback.as_mut() {
+        scrollback.push(ScrollbackLine::from_screen_line(&line));
+    }
+
+    if let Some(handler) = wanting_handler(
+        &data.screen_callbacks,
+        &mut data.screen_handler,
+        |callbacks| callbacks.sb_pushline.is_some(),
+    ) {
+        handler.sb_pushline(&line);
     }
+
+    1
 }
 
 pub extern "C" fn sb_popline(
     cols: c_int,
-    cells_ptr: *const ffi::VTermScreenCell,
-    vterm: *mut c_void,
+    cells_ptr: *mut ffi::VTermScreenCell,
+    data: *mut c_void,
 ) -> c_int {
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => {
-            let mut cells = vec![];
-            for i in 0..(cols as usize) {
-                let ptr = unsafe { ffi::vterm_cell_pointer_arithmetic(cells_ptr, i as c_int) };
-                cells.push(ScreenCell::from_ptr(ptr, &vterm));
-            }
-
-            match tx.send(ScreenEvent::SbPopLine(SbPopLineEvent { cells: cells })) {
-                Ok(_) => 1,
-                Err(_) => 0,
-            }
-        }
+    let data: &mut CallbackData = unsafe { &mut *(data as *mut CallbackData) };
+    let state = unsafe { data.state_ptr.as_ref() };
+
+    // Scrollback, when enabled, is the only source of old lines
+    let cells = match data.scrollback.as_mut() {
+        Some(scrollback) => scrollback.pop().map(|line| line.cells()),
+        None => match wanting_handler(
+            &data.screen_callbacks,
+            &mut data.screen_handler,
+            |callbacks| callbacks.sb_popline.is_some(),
+        ) {
+            Some(handler) => handler.sb_popline(cols as usize),
+            None => None,
+        },
+    };
+    let cells = match cells {
+        Some(cells) => cells,
+        None => return 0,
+    };
+
+    let blank = ScreenCell::blank_with_state(state, &data.palette_lookup);
+    for col in 0..(cols as usize) {
+        let ptr = unsafe { ffi::vterm_cell_pointer_arithmetic(cells_ptr, col as c_int) };
+        cells
+            .get(col)
+            .unwrap_or(&blank)
+            .write_to_ptr(ptr as *mut ffi::VTermScreenCell, &mut data.hyperlinks);
+    }
+
+    1
+}
+
+/// The screen handler, but only if it asked for the callback. The scrollback callbacks are also
+/// registered for the built in Scrollback and set_term_prop to keep the title, so they can fire
+/// when the handler didn't ask.
+/// Takes the fields rather than the CallbackData so other fields can still be borrowed.
+fn wanting_handler<'a, F>(
+    callbacks: &Option<ffi::VTermScreenCallbacks>,
+    handler: &'a mut Option<Box<dyn ScreenHandler>>,
+    wants: F,
+) -> Option<&'a mut Box<dyn ScreenHandler>>
+where
+    F: FnOnce(&ffi::VTermScreenCallbacks) -> bool,
+{
+    match callbacks.as_ref() {
+        Some(callbacks) if wants(callbacks) => handler.as_mut(),
+        _ => None,
+    }
+}
+
+/// Call the given closure with the state and the screen handler, if there is one.
+fn cast_data<F>(data: *mut c_void, closure: F) -> c_int
+where
+    F: FnOnce(&ffi::VTermState, &mut dyn ScreenHandler) -> c_int,
+{
+    let data: &mut CallbackData = unsafe { &mut *(data as *mut CallbackData) };
+    let state = unsafe { data.state_ptr.as_ref() };
+    match data.screen_handler.as_mut() {
+        Some(handler) => closure(state, handler.as_mut()),
         None => 0,
     }
 }
@@ -1,9 +1,10 @@
-use libc::size_t;
+use libc::{c_char, c_int, c_uint, size_t};
+use std::fmt;
 use std::vec::Vec;
 
 use super::*;
 
-#[derive(Debug, Default, PartialEq, Clone)]
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone)]
 pub struct ColorRGB {
     pub red: u8,
     pub green: u8,
@@ -12,6 +13,57 @@ pub struct ColorRGB {
 
 pub type ColorPalette = usize;
 
+/// Where a cell or pen's colour came from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Color {
+    /// The terminal's default foreground or background, as after `SGR 39` or `SGR 49`
+    Default,
+    /// A palette entry, as after `SGR 31` or `SGR 38;5;n`. Bold text can still be drawn with
+    /// the bright version of the first 8.
+    Indexed(u8),
+    /// A colour given directly, as after `SGR 38;2;r;g;b`
+    Rgb(ColorRGB),
+}
+
+impl Color {
+    /// Build from one of libvterm's colour indexes and the rgb it went with
+    pub(crate) fn from_ffi(index: c_int, rgb: &ColorRGB) -> Color {
+        match index {
+            0..=255 => Color::Indexed(index as u8),
+            ffi::VTERM_COLOR_INDEX_RGB => Color::Rgb(rgb.clone()),
+            _ => Color::Default,
+        }
+    }
+
+    pub(crate) fn to_ffi_index(&self) -> c_int {
+        match *self {
+            Color::Default => ffi::VTERM_COLOR_INDEX_DEFAULT,
+            Color::Indexed(index) => index as c_int,
+            Color::Rgb(_) => ffi::VTERM_COLOR_INDEX_RGB,
+        }
+    }
+
+    /// The palette index, or for colours that don't have one the first palette entry that
+    /// matches the rgb
+    pub(crate) fn palette_index(
+        &self,
+        rgb: &ColorRGB,
+        state: &ffi::VTermState,
+        palette_lookup: &PaletteLookup,
+    ) -> ColorPalette {
+        match *self {
+            Color::Indexed(index) => index as ColorPalette,
+            _ => palette_lookup.get(state, rgb),
+        }
+    }
+}
+
+impl Default for Color {
+    fn default() -> Color {
+        Color::Default
+    }
+}
+
 #[derive(Debug, Default, PartialEq, Clone)]
 pub struct ScreenCellAttr {
     pub bold: bool,
@@ -25,29 +77,89 @@ pub struct ScreenCellAttr {
     pub dhl: u8,   // On a DECDHL line (1=top 2=bottom)
 }
 
+impl ScreenCellAttr {
+    pub(crate) fn from_ptr(ptr: *const ffi::VTermScreenCell) -> ScreenCellAttr {
+        unsafe {
+            ScreenCellAttr {
+                bold: int_to_bool(ffi::vterm_cell_get_bold(ptr) as i32),
+                underline: ffi::vterm_cell_get_underline(ptr) as u8,
+                italic: int_to_bool(ffi::vterm_cell_get_italic(ptr) as i32),
+                blink: int_to_bool(ffi::vterm_cell_get_blink(ptr) as i32),
+                reverse: int_to_bool(ffi::vterm_cell_get_reverse(ptr) as i32),
+                strike: int_to_bool(ffi::vterm_cell_get_strike(ptr) as i32),
+                font: ffi::vterm_cell_get_font(ptr) as u8,
+                dwl: int_to_bool(ffi::vterm_cell_get_dwl(ptr) as i32),
+                dhl: ffi::vterm_cell_get_dhl(ptr) as u8,
+            }
+        }
+    }
+
+    pub(crate) fn from_flat(flat: &ffi::VTermFlatCell) -> ScreenCellAttr {
+        ScreenCellAttr {
+            bold: flat.bold != 0,
+            underline: flat.underline,
+            italic: flat.italic != 0,
+            blink: flat.blink != 0,
+            reverse: flat.reverse != 0,
+            strike: flat.strike != 0,
+            font: flat.font,
+            dwl: flat.dwl != 0,
+            dhl: flat.dhl,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct ScreenCell {
-    /// The bytes for the glyph in the cell
-    pub chars: Vec<u8>,
+    /// The glyph's base character followed by any combining characters, in the order they were
+    /// written. Empty for blank cells and the right half of a wide character.
+    pub chars: Vec<char>,
+    /// 2 for wide characters like CJK and most emoji, and 0 for the cell holding the right half
+    /// of one
     pub width: u8,
     pub attrs: ScreenCellAttr,
     /// foreground color in rgb
     pub fg_rgb: ColorRGB,
     /// background color in rgb
     pub bg_rgb: ColorRGB,
-    /// foreground color as a palette index
+    /// foreground color as a palette index. Colors not picked from the palette get the first
+    /// entry with the same rgb, or 0.
     pub fg_palette: ColorPalette,
-    /// background color as a palette index rgb
+    /// background color as a palette index, found the same way as `fg_palette`
     pub bg_palette: ColorPalette,
+    /// Whether the foreground is the default, a palette entry or rgb
+    pub fg: Color,
+    /// Whether the background is the default, a palette entry or rgb
+    pub bg: Color,
+    /// The OSC 8 hyperlink that was open when the cell was written
+    pub hyperlink: Option<Hyperlink>,
 }
 
 impl ScreenCell {
     // Copies data from the given pointer. Doesn't free the pointer or anything.
     pub fn from_ptr(ptr: *const ffi::VTermScreenCell, vterm: &VTerm) -> ScreenCell {
-        let fg_rgb = unsafe { ffi::vterm_cell_get_fg(ptr) };
-        let bg_rgb = unsafe { ffi::vterm_cell_get_bg(ptr) };
+        ScreenCell::from_ptr_with_state(
+            ptr,
+            unsafe { vterm.state_ptr.as_ref() },
+            &unsafe { vterm.callback_data.as_ref() }.palette_lookup,
+            &unsafe { vterm.callback_data.as_ref() }.hyperlinks,
+        )
+    }
 
-        let mut buf = [0 as u32; ffi::VTERM_MAX_CHARS_PER_CELL];
+    /// Like from_ptr but only needs the state, palette lookup and hyperlinks, for use inside
+    /// callbacks
+    pub(crate) fn from_ptr_with_state(
+        ptr: *const ffi::VTermScreenCell,
+        state: &ffi::VTermState,
+        palette_lookup: &PaletteLookup,
+        hyperlinks: &Hyperlinks,
+    ) -> ScreenCell {
+        let fg_rgb = unsafe { ffi::vterm_cell_get_fg(ptr) }.as_color_rgb();
+        let bg_rgb = unsafe { ffi::vterm_cell_get_bg(ptr) }.as_color_rgb();
+        let fg = Color::from_ffi(unsafe { ffi::vterm_cell_get_fg_index(ptr) }, &fg_rgb);
+        let bg = Color::from_ffi(unsafe { ffi::vterm_cell_get_bg_index(ptr) }, &bg_rgb);
+
+        let mut buf = [0u32; ffi::VTERM_MAX_CHARS_PER_CELL];
         let chars_count = unsafe {
             ffi::vterm_cell_get_chars(
                 ptr,
@@ -55,42 +167,153 @@ impl ScreenCell {
                 ffi::VTERM_MAX_CHARS_PER_CELL as size_t,
             )
         };
-        let buf: [u8; ffi::VTERM_MAX_CHARS_PER_CELL * 4] = unsafe { ::std::mem::transmute(buf) };
-        let mut chars: Vec<u8> = vec![];
-        chars.extend_from_slice(&buf[0..chars_count as usize * 4]);
+        let (chars, width) = chars_from_codepoints(&buf[0..chars_count as usize], unsafe {
+            ffi::vterm_cell_get_width(ptr)
+        } as u8);
 
         unsafe {
             ScreenCell {
                 chars: chars,
-                width: ffi::vterm_cell_get_width(ptr) as u8,
-                attrs: ScreenCellAttr {
-                    bold: int_to_bool(ffi::vterm_cell_get_bold(ptr) as i32),
-                    underline: ffi::vterm_cell_get_underline(ptr) as u8,
-                    italic: int_to_bool(ffi::vterm_cell_get_italic(ptr) as i32),
-                    blink: int_to_bool(ffi::vterm_cell_get_blink(ptr) as i32),
-                    reverse: int_to_bool(ffi::vterm_cell_get_reverse(ptr) as i32),
-                    strike: int_to_bool(ffi::vterm_cell_get_strike(ptr) as i32),
-                    font: ffi::vterm_cell_get_font(ptr) as u8,
-                    dwl: int_to_bool(ffi::vterm_cell_get_dwl(ptr) as i32),
-                    dhl: ffi::vterm_cell_get_dhl(ptr) as u8,
-                },
-                fg_rgb: ColorRGB {
-                    red: fg_rgb.red,
-                    green: fg_rgb.green,
-                    blue: fg_rgb.blue,
-                },
-                bg_rgb: ColorRGB {
-                    red: bg_rgb.red,
-                    green: bg_rgb.green,
-                    blue: bg_rgb.blue,
-                },
-                fg_palette: vterm.state_get_palette_color_from_c_rgb(&fg_rgb),
-                bg_palette: vterm.state_get_palette_color_from_c_rgb(&bg_rgb),
+                width: width,
+                attrs: ScreenCellAttr::from_ptr(ptr),
+                fg_palette: fg.palette_index(&fg_rgb, state, palette_lookup),
+                bg_palette: bg.palette_index(&bg_rgb, state, palette_lookup),
+                fg_rgb: fg_rgb,
+                bg_rgb: bg_rgb,
+                fg: fg,
+                bg: bg,
+                hyperlink: hyperlinks.get(ffi::vterm_cell_get_uri(ptr)).cloned(),
             }
         }
     }
 }
 
+impl ScreenCell {
+    /// Like from_ptr_with_state, for a cell read in bulk with `vterm_screen_get_flat_cells`
+    pub(crate) fn from_flat(
+        flat: &ffi::VTermFlatCell,
+        state: &ffi::VTermState,
+        palette_lookup: &PaletteLookup,
+        hyperlinks: &Hyperlinks,
+    ) -> ScreenCell {
+        let fg_rgb = flat.fg.as_color_rgb();
+        let bg_rgb = flat.bg.as_color_rgb();
+        let fg = Color::from_ffi(flat.fg_index, &fg_rgb);
+        let bg = Color::from_ffi(flat.bg_index, &bg_rgb);
+
+        let chars_count = flat.chars.iter().take_while(|&&c| c != 0).count();
+        let (chars, width) = chars_from_codepoints(&flat.chars[..chars_count], flat.width as u8);
+
+        ScreenCell {
+            chars: chars,
+            width: width,
+            attrs: ScreenCellAttr::from_flat(flat),
+            fg_palette: fg.palette_index(&fg_rgb, state, palette_lookup),
+            bg_palette: bg.palette_index(&bg_rgb, state, palette_lookup),
+            fg_rgb: fg_rgb,
+            bg_rgb: bg_rgb,
+            fg: fg,
+            bg: bg,
+            hyperlink: hyperlinks.get(flat.uri).cloned(),
+        }
+    }
+
+    /// An empty cell in the terminal's default colors
+    pub(crate) fn blank_with_state(
+        state: &ffi::VTermState,
+        palette_lookup: &PaletteLookup,
+    ) -> ScreenCell {
+        let mut fg: ffi::VTermColor = Default::default();
+        let mut bg: ffi::VTermColor = Default::default();
+        unsafe { ffi::vterm_state_get_default_colors(state, &mut fg, &mut bg) };
+
+        ScreenCell {
+            chars: vec![],
+            width: 1,
+            attrs: Default::default(),
+            fg_palette: palette_lookup.get(state, &fg.as_color_rgb()),
+            bg_palette: palette_lookup.get(state, &bg.as_color_rgb()),
+            fg_rgb: fg.as_color_rgb(),
+            bg_rgb: bg.as_color_rgb(),
+            fg: Color::Default,
+            bg: Color::Default,
+            hyperlink: None,
+        }
+    }
+
+    /// Copy this cell into a libvterm cell, the reverse of from_ptr. The palette indexes are
+    /// ignored in favour of `fg` and `bg`. A width of 0 is written as 1 because libvterm steps
+    /// through a line by cell width.
+    pub(crate) fn write_to_ptr(&self, ptr: *mut ffi::VTermScreenCell, hyperlinks: &mut Hyperlinks) {
+        let mut buf = [0u32; ffi::VTERM_MAX_CHARS_PER_CELL];
+        for (i, &c) in self
+            .chars
+            .iter()
+            .take(ffi::VTERM_MAX_CHARS_PER_CELL)
+            .enumerate()
+        {
+            buf[i] = c as u32;
+        }
+
+        unsafe {
+            ffi::vterm_cell_set_chars(ptr, buf.as_ptr(), ffi::VTERM_MAX_CHARS_PER_CELL as size_t);
+            ffi::vterm_cell_set_width(ptr, self.width.max(1) as c_char);
+            ffi::vterm_cell_set_bold(ptr, self.attrs.bold as c_uint);
+            ffi::vterm_cell_set_underline(ptr, self.attrs.underline as c_uint);
+            ffi::vterm_cell_set_italic(ptr, self.attrs.italic as c_uint);
+            ffi::vterm_cell_set_blink(ptr, self.attrs.blink as c_uint);
+            ffi::vterm_cell_set_reverse(ptr, self.attrs.reverse as c_uint);
+            ffi::vterm_cell_set_strike(ptr, self.attrs.strike as c_uint);
+            ffi::vterm_cell_set_font(ptr, self.attrs.font as c_uint);
+            ffi::vterm_cell_set_dwl(ptr, self.attrs.dwl as c_uint);
+            ffi::vterm_cell_set_dhl(ptr, self.attrs.dhl as c_uint);
+            ffi::vterm_cell_set_fg(ptr, ffi::VTermColor::from_color_rgb(&self.fg_rgb));
+            ffi::vterm_cell_set_bg(ptr, ffi::VTermColor::from_color_rgb(&self.bg_rgb));
+            ffi::vterm_cell_set_fg_index(ptr, self.fg.to_ffi_index());
+            ffi::vterm_cell_set_bg_index(ptr, self.bg.to_ffi_index());
+            ffi::vterm_cell_set_uri(
+                ptr,
+                self.hyperlink
+                    .as_ref()
+                    .map_or(0, |hyperlink| hyperlinks.intern(hyperlink)),
+            );
+        }
+    }
+}
+
+impl ScreenCell {
+    /// The cell's text, which is empty for blank cells and the right half of a wide character
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+}
+
+/// Writes the text the cell shows, so a row of cells displays as the line does. Blank cells are
+/// a space and the right half of a wide character is nothing, since the left half covers it.
+impl fmt::Display for ScreenCell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.chars.is_empty(), self.width) {
+            (true, 0) => Ok(()),
+            (true, _) => f.write_str(" "),
+            (false, _) => f.write_str(&self.text()),
+        }
+    }
+}
+
+/// Turn libvterm's codepoints into chars. The right half of a wide character is a codepoint of
+/// -1, which becomes no chars and a width of 0.
+pub(crate) fn chars_from_codepoints(codepoints: &[u32], width: u8) -> (Vec<char>, u8) {
+    if codepoints.first() == Some(&0xffff_ffff) {
+        return (vec![], 0);
+    }
+
+    let chars = codepoints
+        .iter()
+        .filter_map(|&c| ::std::char::from_u32(c))
+        .collect();
+    (chars, width)
+}
+
 impl Default for ScreenCell {
     fn default() -> ScreenCell {
         ScreenCell {
@@ -109,6 +332,9 @@ impl Default for ScreenCell {
             },
             fg_palette: 7,
             bg_palette: 0,
+            fg: Color::Default,
+            bg: Color::Default,
+            hyperlink: None,
         }
     }
 }
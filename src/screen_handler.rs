@@ -0,0 +1,160 @@
+#![allow(unused_variables)]
+
+use libc::c_int;
+use std::sync::mpsc;
+
+use super::*;
+
+/// Receives screen callbacks from libvterm as they happen. Every method has a no-op default, so
+/// implement only the ones you care about. Methods that return a value hand it straight back to
+/// libvterm, and the defaults behave as if the callback wasn't registered at all.
+///
+/// Register one with `VTerm::set_screen_handler`.
+pub trait ScreenHandler {
+    fn damage(&mut self, rect: Rect) {}
+
+    /// Return true if the move was handled. Otherwise libvterm falls back to damaging `dest`.
+    fn move_rect(&mut self, dest: Rect, src: Rect) -> bool {
+        false
+    }
+
+    fn move_cursor(&mut self, new: Pos, old: Pos, is_visible: bool) {}
+
+    /// Return false to reject the change. libvterm then doesn't store the new value, which is
+    /// how switching to the alt screen can be refused.
+    fn set_term_prop(&mut self, prop: TermProp) -> bool {
+        true
+    }
+
+    fn bell(&mut self) {}
+
+    fn resize(&mut self, size: Size) {}
+
+    /// A line is scrolling off the top of the screen.
+    fn sb_pushline(&mut self, line: &ScreenLine) {}
+
+    /// The screen grew taller and libvterm is asking for the most recent line of scrollback back,
+    /// `cols` cells wide. Return None when there's nothing to restore. A line shorter than `cols`
+    /// is padded with blank cells and a longer one is cut off.
+    fn sb_popline(&mut self, cols: usize) -> Option<Vec<ScreenCell>> {
+        None
+    }
+}
+
+/// A row of cells libvterm passes to the scrollback callbacks. Cells are converted only when
+/// asked for, so handlers that ignore the line don't pay for it.
+pub struct ScreenLine<'a> {
+    pub(crate) cells_ptr: *const ffi::VTermScreenCell,
+    pub(crate) cols: usize,
+    pub(crate) state: &'a ffi::VTermState,
+    pub(crate) palette_lookup: &'a PaletteLookup,
+    pub(crate) hyperlinks: &'a Hyperlinks,
+}
+
+impl<'a> ScreenLine<'a> {
+    pub(crate) fn new(
+        cells_ptr: *const ffi::VTermScreenCell,
+        cols: usize,
+        state: &'a ffi::VTermState,
+        palette_lookup: &'a PaletteLookup,
+        hyperlinks: &'a Hyperlinks,
+    ) -> ScreenLine<'a> {
+        ScreenLine {
+            cells_ptr: cells_ptr,
+            cols: cols,
+            state: state,
+            palette_lookup: palette_lookup,
+            hyperlinks: hyperlinks,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cols
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cols == 0
+    }
+
+    /// Return the cell in the given column, or None if it's past the end of the line
+    pub fn get(&self, col: usize) -> Option<ScreenCell> {
+        if col >= self.cols {
+            return None;
+        }
+
+        let ptr = unsafe { ffi::vterm_cell_pointer_arithmetic(self.cells_ptr, col as c_int) };
+        Some(ScreenCell::from_ptr_with_state(
+            ptr,
+            self.state,
+            self.palette_lookup,
+            self.hyperlinks,
+        ))
+    }
+
+    pub fn to_vec(&self) -> Vec<ScreenCell> {
+        (0..self.cols).filter_map(|col| self.get(col)).collect()
+    }
+}
+
+/// The channel api. Each callback is turned into a ScreenEvent and sent.
+impl ScreenHandler for mpsc::Sender<ScreenEvent> {
+    fn damage(&mut self, rect: Rect) {
+        let _ = self.send(ScreenEvent::Damage(DamageEvent { rect: rect }));
+    }
+
+    fn move_rect(&mut self, dest: Rect, src: Rect) -> bool {
+        self.send(ScreenEvent::MoveRect(MoveRectEvent {
+            dest: dest,
+            src: src,
+        }))
+        .is_ok()
+    }
+
+    fn move_cursor(&mut self, new: Pos, old: Pos, is_visible: bool) {
+        let _ = self.send(ScreenEvent::MoveCursor(MoveCursorEvent {
+            new: new,
+            old: old,
+            is_visible: is_visible,
+        }));
+    }
+
+    fn set_term_prop(&mut self, prop: TermProp) -> bool {
+        let event = match prop {
+            TermProp::AltScreen(is_on) => ScreenEvent::AltScreen(AltScreenEvent { is_on: is_on }),
+            TermProp::CursorBlink(is_on) => {
+                ScreenEvent::CursorBlink(CursorBlinkEvent { is_on: is_on })
+            }
+            TermProp::CursorShape(shape) => {
+                ScreenEvent::CursorShape(CursorShapeEvent { shape: shape })
+            }
+            TermProp::CursorVisible(is_on) => {
+                ScreenEvent::CursorVisible(CursorVisibleEvent { is_on: is_on })
+            }
+            TermProp::IconName(name) => ScreenEvent::IconName(IconNameEvent { name: name }),
+            TermProp::Mouse(mode) => ScreenEvent::Mouse(MouseEvent { mode: mode }),
+            TermProp::Reverse(is_on) => ScreenEvent::Reverse(ReverseEvent { is_on: is_on }),
+            TermProp::Title(title) => ScreenEvent::Title(TitleEvent { title: title }),
+        };
+
+        self.send(event).is_ok()
+    }
+
+    fn bell(&mut self) {
+        let _ = self.send(ScreenEvent::Bell);
+    }
+
+    fn resize(&mut self, size: Size) {
+        let _ = self.send(ScreenEvent::Resize(ResizeEvent { size: size }));
+    }
+
+    fn sb_pushline(&mut self, line: &ScreenLine) {
+        let _ = self.send(ScreenEvent::SbPushLine(SbPushLineEvent {
+            cells: line.to_vec(),
+        }));
+    }
+
+    fn sb_popline(&mut self, cols: usize) -> Option<Vec<ScreenCell>> {
+        let _ = self.send(ScreenEvent::SbPopLine(SbPopLineEvent { cols: cols }));
+        None
+    }
+}
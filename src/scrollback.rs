@@ -0,0 +1,437 @@
+use libc::{c_int, size_t};
+use std::collections::VecDeque;
+use std::mem;
+
+use super::*;
+
+/// How much history a Scrollback keeps before dropping its oldest lines.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ScrollbackLimit {
+    Lines(usize),
+    /// An approximate cap on the memory used by the stored lines
+    Bytes(usize),
+}
+
+/// Lines that scrolled off the top of the screen. Enable it with `VTerm::screen_set_scrollback`
+/// and it fills itself from libvterm's sb_pushline callback and hands lines back on sb_popline,
+/// so growing the terminal brings history back.
+///
+/// Lines are indexed from the oldest, at 0, to the most recent.
+#[derive(Debug, Clone)]
+pub struct Scrollback {
+    lines: VecDeque<ScrollbackLine>,
+    limit: ScrollbackLimit,
+    bytes: usize,
+}
+
+impl Scrollback {
+    pub fn new(limit: ScrollbackLimit) -> Scrollback {
+        Scrollback {
+            lines: VecDeque::new(),
+            limit: limit,
+            bytes: 0,
+        }
+    }
+
+    pub fn with_max_lines(max_lines: usize) -> Scrollback {
+        Scrollback::new(ScrollbackLimit::Lines(max_lines))
+    }
+
+    pub fn with_max_bytes(max_bytes: usize) -> Scrollback {
+        Scrollback::new(ScrollbackLimit::Bytes(max_bytes))
+    }
+
+    pub fn limit(&self) -> ScrollbackLimit {
+        self.limit
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Roughly how much memory the stored lines use
+    pub fn size_in_bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn line(&self, index: usize) -> Option<&ScrollbackLine> {
+        self.lines.get(index)
+    }
+
+    /// Iterate from the oldest line to the most recent
+    pub fn iter(&self) -> ScrollbackIter<'_> {
+        ScrollbackIter {
+            inner: self.lines.iter(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.bytes = 0;
+    }
+
+    pub fn push(&mut self, line: ScrollbackLine) {
+        self.bytes += line.size_in_bytes();
+        self.lines.push_back(line);
+
+        loop {
+            let is_over = match self.limit {
+                ScrollbackLimit::Lines(max) => self.lines.len() > max,
+                ScrollbackLimit::Bytes(max) => self.bytes > max,
+            };
+            if !is_over {
+                break;
+            }
+
+            match self.lines.pop_front() {
+                Some(old) => self.bytes -= old.size_in_bytes(),
+                None => break,
+            }
+        }
+    }
+
+    /// Remove and return the most recent line
+    pub fn pop(&mut self) -> Option<ScrollbackLine> {
+        let line = self.lines.pop_back()?;
+        self.bytes -= line.size_in_bytes();
+        Some(line)
+    }
+}
+
+impl<'a> IntoIterator for &'a Scrollback {
+    type Item = &'a ScrollbackLine;
+    type IntoIter = ScrollbackIter<'a>;
+
+    fn into_iter(self) -> ScrollbackIter<'a> {
+        self.iter()
+    }
+}
+
+pub struct ScrollbackIter<'a> {
+    inner: ::std::collections::vec_deque::Iter<'a, ScrollbackLine>,
+}
+
+impl<'a> Iterator for ScrollbackIter<'a> {
+    type Item = &'a ScrollbackLine;
+
+    fn next(&mut self) -> Option<&'a ScrollbackLine> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for ScrollbackIter<'a> {
+    fn next_back(&mut self) -> Option<&'a ScrollbackLine> {
+        self.inner.next_back()
+    }
+}
+
+impl<'a> ExactSizeIterator for ScrollbackIter<'a> {}
+
+/// One line of scrollback. Trailing blank cells are dropped, the characters of every cell share
+/// one buffer and runs of cells with the same pen store it once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScrollbackLine {
+    cols: usize,
+    chars: Box<[u32]>,
+    /// How many of `chars` each stored cell uses, and its width
+    cells: Box<[(u8, u8)]>,
+    /// The first column of each pen run, and the pen
+    pens: Box<[(usize, Pen)]>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Pen {
+    attrs: ScreenCellAttr,
+    fg_rgb: ColorRGB,
+    bg_rgb: ColorRGB,
+    fg_palette: u8,
+    bg_palette: u8,
+    fg: Color,
+    bg: Color,
+    hyperlink: Option<Hyperlink>,
+}
+
+impl ScrollbackLine {
+    pub(crate) fn from_screen_line(line: &ScreenLine) -> ScrollbackLine {
+        let state = line.state;
+        let mut default_fg: ffi::VTermColor = Default::default();
+        let mut default_bg: ffi::VTermColor = Default::default();
+        unsafe { ffi::vterm_state_get_default_colors(state, &mut default_fg, &mut default_bg) };
+
+        // Find where the trailing cells that are indistinguishable from a cleared cell start
+        let mut end = line.len();
+        while end > 0 {
+            let ptr =
+                unsafe { ffi::vterm_cell_pointer_arithmetic(line.cells_ptr, end as c_int - 1) };
+            let mut buf = [0u32; ffi::VTERM_MAX_CHARS_PER_CELL];
+            let is_blank = unsafe {
+                ffi::vterm_cell_get_chars(
+                    ptr,
+                    buf.as_mut_ptr(),
+                    ffi::VTERM_MAX_CHARS_PER_CELL as size_t,
+                ) == 0
+                    && ScreenCellAttr::from_ptr(ptr) == ScreenCellAttr::default()
+                    && ffi::vterm_cell_get_fg(ptr) == default_fg
+                    && ffi::vterm_cell_get_bg(ptr) == default_bg
+                    && ffi::vterm_cell_get_fg_index(ptr) == ffi::VTERM_COLOR_INDEX_DEFAULT
+                    && ffi::vterm_cell_get_bg_index(ptr) == ffi::VTERM_COLOR_INDEX_DEFAULT
+                    && ffi::vterm_cell_get_uri(ptr) == 0
+            };
+            if !is_blank {
+                break;
+            }
+            end -= 1;
+        }
+
+        let mut chars = vec![];
+        let mut cells = Vec::with_capacity(end);
+        let mut pens: Vec<(usize, Pen)> = vec![];
+
+        for col in 0..end {
+            let ptr = unsafe { ffi::vterm_cell_pointer_arithmetic(line.cells_ptr, col as c_int) };
+
+            let mut buf = [0u32; ffi::VTERM_MAX_CHARS_PER_CELL];
+            let count = unsafe {
+                ffi::vterm_cell_get_chars(
+                    ptr,
+                    buf.as_mut_ptr(),
+                    ffi::VTERM_MAX_CHARS_PER_CELL as size_t,
+                )
+            };
+            chars.extend_from_slice(&buf[0..count as usize]);
+            cells.push((count as u8, unsafe { ffi::vterm_cell_get_width(ptr) } as u8));
+
+            let attrs = ScreenCellAttr::from_ptr(ptr);
+            let fg_rgb = unsafe { ffi::vterm_cell_get_fg(ptr) }.as_color_rgb();
+            let bg_rgb = unsafe { ffi::vterm_cell_get_bg(ptr) }.as_color_rgb();
+            let fg = Color::from_ffi(unsafe { ffi::vterm_cell_get_fg_index(ptr) }, &fg_rgb);
+            let bg = Color::from_ffi(unsafe { ffi::vterm_cell_get_bg_index(ptr) }, &bg_rgb);
+            let hyperlink = line.hyperlinks.get(unsafe { ffi::vterm_cell_get_uri(ptr) });
+            let is_same_pen = match pens.last() {
+                Some((_, pen)) => {
+                    pen.attrs == attrs
+                        && pen.fg_rgb == fg_rgb
+                        && pen.bg_rgb == bg_rgb
+                        && pen.fg == fg
+                        && pen.bg == bg
+                        && pen.hyperlink.as_ref() == hyperlink
+                }
+                None => false,
+            };
+            if !is_same_pen {
+                let fg_palette = fg.palette_index(&fg_rgb, state, line.palette_lookup);
+                let bg_palette = bg.palette_index(&bg_rgb, state, line.palette_lookup);
+                pens.push((
+                    col,
+                    Pen {
+                        attrs: attrs,
+                        fg_rgb: fg_rgb,
+                        bg_rgb: bg_rgb,
+                        fg_palette: fg_palette as u8,
+                        bg_palette: bg_palette as u8,
+                        fg: fg,
+                        bg: bg,
+                        hyperlink: hyperlink.cloned(),
+                    },
+                ));
+            }
+        }
+
+        ScrollbackLine {
+            cols: line.len(),
+            chars: chars.into_boxed_slice(),
+            cells: cells.into_boxed_slice(),
+            pens: pens.into_boxed_slice(),
+        }
+    }
+
+    /// The width of the screen when the line was pushed
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Return the stored cells. Trailing blank cells aren't stored so there may be fewer than
+    /// `cols`.
+    pub fn cells(&self) -> Vec<ScreenCell> {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        let mut chars_start = 0;
+        let mut pen_index = 0;
+
+        for (col, &(count, width)) in self.cells.iter().enumerate() {
+            if pen_index + 1 < self.pens.len() && self.pens[pen_index + 1].0 == col {
+                pen_index += 1;
+            }
+            let pen = &self.pens[pen_index].1;
+
+            let (chars, width) = chars_from_codepoints(
+                &self.chars[chars_start..chars_start + count as usize],
+                width,
+            );
+            chars_start += count as usize;
+
+            cells.push(ScreenCell {
+                chars: chars,
+                width: width,
+                attrs: pen.attrs.clone(),
+                fg_rgb: pen.fg_rgb.clone(),
+                bg_rgb: pen.bg_rgb.clone(),
+                fg_palette: pen.fg_palette as ColorPalette,
+                bg_palette: pen.bg_palette as ColorPalette,
+                fg: pen.fg.clone(),
+                bg: pen.bg.clone(),
+                hyperlink: pen.hyperlink.clone(),
+            });
+        }
+
+        cells
+    }
+
+    /// The text of the line. Empty cells become spaces and trailing whitespace is trimmed.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        let mut chars_start = 0;
+
+        for &(count, _) in self.cells.iter() {
+            let chars = &self.chars[chars_start..chars_start + count as usize];
+            chars_start += count as usize;
+
+            match chars.first() {
+                // The right half of a wide character
+                Some(&0xffff_ffff) => {}
+                Some(_) => text.extend(chars.iter().filter_map(|&c| ::std::char::from_u32(c))),
+                None => text.push(' '),
+            }
+        }
+
+        text.trim_end().to_string()
+    }
+
+    /// Roughly how much memory the line uses
+    pub fn size_in_bytes(&self) -> usize {
+        mem::size_of::<ScrollbackLine>()
+            + self.chars.len() * mem::size_of::<u32>()
+            + self.cells.len() * mem::size_of::<(u8, u8)>()
+            + self.pens.len() * mem::size_of::<(usize, Pen)>()
+    }
+}
+
+impl VTerm {
+    /// Keep lines that scroll off the top of the screen in the given Scrollback, replacing any
+    /// already set. When the screen grows taller lines are taken back out of it, and screen
+    /// handlers are no longer asked for them.
+    pub fn screen_set_scrollback(&mut self, scrollback: Scrollback) {
+        unsafe { self.callback_data.as_mut() }.scrollback = Some(scrollback);
+        self.register_screen_callbacks();
+    }
+
+    /// Stop keeping scrollback and return what was kept
+    pub fn screen_take_scrollback(&mut self) -> Option<Scrollback> {
+        let scrollback = unsafe { self.callback_data.as_mut() }.scrollback.take();
+        self.register_screen_callbacks();
+        scrollback
+    }
+
+    pub fn screen_scrollback(&self) -> Option<&Scrollback> {
+        unsafe { self.callback_data.as_ref() }.scrollback.as_ref()
+    }
+
+    /// The number of lines of scrollback, or 0 if it isn't enabled
+    pub fn scrollback_len(&self) -> usize {
+        self.screen_scrollback()
+            .map_or(0, |scrollback| scrollback.len())
+    }
+
+    /// Return a line of scrollback, where 0 is the oldest
+    pub fn scrollback_line(&self, index: usize) -> Option<&ScrollbackLine> {
+        self.screen_scrollback()?.line(index)
+    }
+
+    /// Iterate over the scrollback from the oldest line to the most recent. Empty if scrollback
+    /// isn't enabled.
+    pub fn scrollback_lines(&self) -> impl DoubleEndedIterator<Item = &ScrollbackLine> {
+        self.screen_scrollback().into_iter().flat_map(|s| s.iter())
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::super::*;
+    use std::io::prelude::*;
+
+    #[test]
+    fn scrollback_drops_oldest_lines_over_the_line_limit() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 5,
+        })
+        .unwrap();
+        vterm.screen_set_scrollback(Scrollback::with_max_lines(2));
+        vterm.write(b"one\r\ntwo\r\nsix\r\nten\r\nend").unwrap();
+
+        assert_eq!(vterm.scrollback_len(), 2);
+        let text: Vec<String> = vterm.scrollback_lines().map(|line| line.text()).collect();
+        assert_eq!(text, vec!["two", "six"]);
+    }
+
+    #[test]
+    fn scrollback_drops_oldest_lines_over_the_byte_limit() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 5,
+        })
+        .unwrap();
+        vterm.screen_set_scrollback(Scrollback::with_max_bytes(0));
+        vterm.write(b"one\r\ntwo\r\nsix").unwrap();
+        assert_eq!(vterm.scrollback_len(), 0);
+
+        vterm.screen_set_scrollback(Scrollback::with_max_bytes(1000));
+        vterm.write(b"\r\nten").unwrap();
+        let size = vterm.screen_scrollback().unwrap().size_in_bytes();
+        assert!(size > 0);
+        assert!(size <= 1000);
+    }
+
+    #[test]
+    fn scrollback_line_trims_trailing_blank_cells() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 10,
+        })
+        .unwrap();
+        vterm.screen_set_scrollback(Scrollback::with_max_lines(10));
+        vterm.write(b"a\x1b[1mb\r\n\r\n").unwrap();
+
+        let line = vterm.scrollback_line(0).unwrap();
+        assert_eq!(line.cols(), 10);
+        let cells = line.cells();
+        assert_eq!(cells.len(), 2);
+        assert!(!cells[0].attrs.bold);
+        assert!(cells[1].attrs.bold);
+        assert_eq!(line.text(), "ab");
+    }
+
+    #[test]
+    fn scrollback_iter_knows_its_len() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 5,
+        })
+        .unwrap();
+        vterm.screen_set_scrollback(Scrollback::with_max_lines(10));
+        vterm.write(b"one\r\ntwo\r\nsix\r\nten").unwrap();
+
+        let scrollback = vterm.screen_scrollback().unwrap();
+        let mut iter = scrollback.iter();
+        assert_eq!(iter.len(), 2);
+        iter.next_back();
+        assert_eq!(iter.len(), 1);
+    }
+}
@@ -0,0 +1,220 @@
+use libc::c_int;
+use std::io::{self, Write};
+
+use super::*;
+
+// The mouse_flags and mouse_protocol values in libvterm's state
+const MOUSE_WANT_DRAG: c_int = 0x02;
+const MOUSE_WANT_MOVE: c_int = 0x04;
+const MOUSE_UTF8: c_int = 1;
+const MOUSE_SGR: c_int = 2;
+const MOUSE_RXVT: c_int = 3;
+
+impl VTerm {
+    /// Write the escape sequences that bring a terminal, whatever it was showing, to this one's
+    /// state: every cell, the cursor, its shape and visibility, the pen, DEC and ANSI modes
+    /// including mouse tracking, bracketed paste and the keypad, the scroll region, tab stops,
+    /// the title, line attributes and palette colours programs have changed. This is what a
+    /// client reattaching to a session needs to be repainted.
+    ///
+    /// Only the screen on show is included, so while the alt screen is active the normal screen's
+    /// contents are lost. The saved cursor and character sets aren't included either. Cells in the
+    /// default or palette colours come out in those colours as they are now, not as they were when
+    /// the cells were written.
+    pub fn serialize_state(&self, out: &mut dyn Write) -> io::Result<()> {
+        let snapshot = self.snapshot();
+        let size = snapshot.size;
+        let state = unsafe { self.state_ptr.as_ref() };
+        let data = unsafe { self.callback_data.as_ref() };
+        let mode = |get: unsafe extern "C" fn(*const ffi::VTermState) -> c_int| {
+            int_to_bool(unsafe { get(state) })
+        };
+        let mut buf: Vec<u8> = vec![];
+
+        // With DECSCNM on, libvterm flips every cell's reverse attribute as it reads it, and it'll
+        // flip them again on the other end, so they're drawn as stored
+        let reverse = mode(ffi::vterm_state_get_reverse);
+        let mut drawn = snapshot.clone();
+        if reverse {
+            for cell in &mut drawn.cells {
+                cell.attrs.reverse = !cell.attrs.reverse;
+            }
+        }
+
+        // Get to a known place to draw from: the right screen, no insert mode or origin mode and
+        // no margins to keep the cursor in
+        buf.extend_from_slice(if snapshot.alt_screen {
+            b"\x1b[?1049h"
+        } else {
+            b"\x1b[?1049l"
+        });
+        buf.extend_from_slice(b"\x1b[4l\x1b[?6l\x1b[?69l\x1b[r\x1b[?7h");
+
+        // Colours the embedder set are the client's business, so only those programs changed are
+        // sent
+        let palette = self.state_get_palette();
+        for (index, (color, base)) in palette
+            .colors
+            .iter()
+            .zip(data.palette.colors.iter())
+            .enumerate()
+        {
+            if color != base {
+                write!(buf, "\x1b]4;{};{}\x1b\\", index, x11_color(color))?;
+            }
+        }
+        if palette.default_fg != data.palette.default_fg {
+            write!(buf, "\x1b]10;{}\x1b\\", x11_color(&palette.default_fg))?;
+        }
+        if palette.default_bg != data.palette.default_bg {
+            write!(buf, "\x1b]11;{}\x1b\\", x11_color(&palette.default_bg))?;
+        }
+
+        buf.extend_from_slice(b"\x1b[3g");
+        for x in 0..size.width {
+            if int_to_bool(unsafe { ffi::vterm_state_get_tabstop(state, x as c_int) }) {
+                write!(buf, "\x1b[1;{}H\x1bH", x + 1)?;
+            }
+        }
+
+        // Blanks are erased rather than written as spaces, so a VTerm on the other end ends up
+        // with the same cells
+        let mut renderer = Renderer::new();
+        renderer.erase_blanks = true;
+        renderer.draw(&drawn, &mut buf)?;
+
+        // Setting a line's attributes marks its cells and erases the right half of a double
+        // width line with the pen, so that's set to match the erased cells first
+        for (y, row) in drawn.rows().enumerate() {
+            let sequence = match (row[0].attrs.dwl, row[0].attrs.dhl) {
+                (_, 1) => "\x1b#3",
+                (_, 2) => "\x1b#4",
+                (true, _) => "\x1b#6",
+                _ => continue,
+            };
+            renderer.set_pen(&Pen::from_cell(&row[size.width / 2]), &mut buf);
+            write!(buf, "\x1b[{}H{}", y + 1, sequence)?;
+        }
+
+        write!(buf, "\x1b]2;{}\x1b\\", snapshot.title)?;
+
+        let mut region = ffi::VTermRect {
+            start_row: 0,
+            end_row: 0,
+            start_col: 0,
+            end_col: 0,
+        };
+        unsafe { ffi::vterm_state_get_scrollregion(state, &mut region) };
+        let region = region.as_rect();
+        if region.top() != 0 || region.bottom() != size.height {
+            write!(buf, "\x1b[{};{}r", region.top() + 1, region.bottom())?;
+        }
+        if mode(ffi::vterm_state_get_leftrightmargin) {
+            buf.extend_from_slice(b"\x1b[?69h");
+            if region.left() != 0 || region.right() != size.width {
+                write!(buf, "\x1b[{};{}s", region.left() + 1, region.right())?;
+            }
+        }
+
+        // Setting origin mode homes the cursor, so it comes before the cursor is placed
+        let origin = mode(ffi::vterm_state_get_origin);
+        if origin {
+            buf.extend_from_slice(b"\x1b[?6h");
+        }
+        let cup = |pos: Pos| {
+            if origin {
+                format!(
+                    "\x1b[{};{}H",
+                    pos.y.saturating_sub(region.top()) + 1,
+                    pos.x.saturating_sub(region.left()) + 1
+                )
+            } else {
+                format!("\x1b[{};{}H", pos.y + 1, pos.x + 1)
+            }
+        };
+
+        // When the cursor is waiting to wrap, the only way to get there is to write the last
+        // glyph again. A blank is written as a space and erased, which leaves the cursor waiting.
+        let mut cursor = snapshot.cursor_pos;
+        if mode(ffi::vterm_state_get_at_phantom) {
+            if cursor.x > 0 && drawn.cell(&cursor).map_or(false, |cell| cell.width == 0) {
+                cursor.x -= 1;
+            }
+            let cell = drawn.cell(&cursor).cloned().unwrap_or_default();
+            renderer.set_pen(&Pen::from_cell(&cell), &mut buf);
+            if cell.chars.is_empty() {
+                write!(buf, "{} \x1b[X", cup(cursor))?;
+            } else {
+                write!(buf, "{}{}", cup(cursor), cell)?;
+            }
+        } else {
+            buf.extend_from_slice(cup(cursor).as_bytes());
+        }
+
+        // Modes that change how text is written go after the glyph above
+        buf.extend_from_slice(if mode(ffi::vterm_state_get_insert) {
+            b"\x1b[4h"
+        } else {
+            b"\x1b[4l"
+        });
+        if !mode(ffi::vterm_state_get_autowrap) {
+            buf.extend_from_slice(b"\x1b[?7l");
+        }
+        buf.extend_from_slice(if mode(ffi::vterm_state_get_newline) {
+            b"\x1b[20h"
+        } else {
+            b"\x1b[20l"
+        });
+
+        // The renderer leaves the shape of a hidden cursor alone, and DECSCUSR sets blinking too
+        let decscusr = match snapshot.cursor_shape {
+            CursorShape::Block => 2,
+            CursorShape::Underline => 4,
+            CursorShape::BarLeft => 6,
+        };
+        let blink = mode(ffi::vterm_state_get_cursor_blink) as i32;
+        write!(buf, "\x1b[{} q", decscusr - blink)?;
+
+        let dec_modes = [
+            (1, mode(ffi::vterm_state_get_cursor_keys)),
+            (5, reverse),
+            (2004, mode(ffi::vterm_state_get_bracketpaste)),
+        ];
+        for &(number, is_set) in &dec_modes {
+            write!(buf, "\x1b[?{}{}", number, if is_set { 'h' } else { 'l' })?;
+        }
+        buf.extend_from_slice(if mode(ffi::vterm_state_get_keypad) {
+            b"\x1b="
+        } else {
+            b"\x1b>"
+        });
+
+        // Turning tracking on resets the protocol, so the protocol goes second
+        let mouse_flags = unsafe { ffi::vterm_state_get_mouse_flags(state) };
+        buf.extend_from_slice(if mouse_flags & MOUSE_WANT_MOVE != 0 {
+            b"\x1b[?1003h"
+        } else if mouse_flags & MOUSE_WANT_DRAG != 0 {
+            b"\x1b[?1002h"
+        } else if mouse_flags != 0 {
+            b"\x1b[?1000h"
+        } else {
+            b"\x1b[?1000l"
+        });
+        buf.extend_from_slice(
+            match unsafe { ffi::vterm_state_get_mouse_protocol(state) } {
+                MOUSE_UTF8 => b"\x1b[?1005h",
+                MOUSE_SGR => b"\x1b[?1006h",
+                MOUSE_RXVT => b"\x1b[?1015h",
+                // Other terminals keep these modes apart, so a client could be left in any
+                _ => b"\x1b[?1005l\x1b[?1006l\x1b[?1015l",
+            },
+        );
+
+        let mut pen: ffi::VTermFlatCell = Default::default();
+        unsafe { ffi::vterm_state_get_pen_cell(state, &mut pen) };
+        let pen = ScreenCell::from_flat(&pen, state, &data.palette_lookup, &data.hyperlinks);
+        renderer.set_pen(&Pen::from_cell(&pen), &mut buf);
+
+        out.write_all(&buf)
+    }
+}
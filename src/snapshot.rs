@@ -0,0 +1,306 @@
+use std::borrow::Cow;
+
+use super::*;
+
+/// An owned copy of everything on the screen at one moment. It doesn't refer back to the VTerm,
+/// so it can be kept, compared or sent to another thread to be drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub size: Size,
+    /// Every cell on the screen, row by row
+    pub cells: Vec<ScreenCell>,
+    pub cursor_pos: Pos,
+    pub cursor_visible: bool,
+    pub cursor_shape: CursorShape,
+    /// The last title set with OSC 0 or 2 that no handler turned down
+    pub title: String,
+    pub alt_screen: bool,
+}
+
+impl Snapshot {
+    /// The cell at the given position, or None if it's off the screen
+    pub fn cell(&self, pos: &Pos) -> Option<&ScreenCell> {
+        if pos.x >= self.size.width || pos.y >= self.size.height {
+            return None;
+        }
+        self.cells.get(pos.y * self.size.width + pos.x)
+    }
+
+    /// The cells of each row in turn
+    pub fn rows(&self) -> impl Iterator<Item = &[ScreenCell]> {
+        self.cells.chunks(self.size.width.max(1))
+    }
+
+    /// The changes that turn this snapshot into `other`. Scrolled rows come first as MoveRects,
+    /// then what's left is sent as whole rows when most of a row changed, otherwise as runs of
+    /// changed cells. Runs whose text is the same are sent as Attrs so a renderer can skip
+    /// redrawing glyphs.
+    pub fn diff(&self, other: &Snapshot) -> Vec<Change> {
+        let mut changes: Vec<Change> = vec![];
+
+        if self.size != other.size {
+            changes.push(Change::Resize(other.size));
+            for (y, row) in other.rows().enumerate() {
+                changes.push(Change::Row {
+                    y: y,
+                    cells: row.to_vec(),
+                });
+            }
+        } else if self.size.width > 0 {
+            // The cells as they'll be once the scrolls are applied
+            let mut cells: Cow<[ScreenCell]> = Cow::Borrowed(&self.cells);
+            while let Some((dest, src)) = find_scroll(&cells, &other.cells, self.size) {
+                move_rect(cells.to_mut(), self.size.width, &dest, &src);
+                changes.push(Change::MoveRect {
+                    dest: dest,
+                    src: src,
+                });
+            }
+
+            let width = self.size.width;
+            for (y, (old, new)) in cells
+                .chunks(width)
+                .zip(other.cells.chunks(width))
+                .enumerate()
+            {
+                diff_row(old, new, y, &mut changes);
+            }
+        }
+
+        if self.cursor_pos != other.cursor_pos
+            || self.cursor_visible != other.cursor_visible
+            || self.cursor_shape != other.cursor_shape
+        {
+            changes.push(Change::Cursor {
+                pos: other.cursor_pos,
+                visible: other.cursor_visible,
+                shape: other.cursor_shape.clone(),
+            });
+        }
+        if self.title != other.title {
+            changes.push(Change::Title(other.title.clone()));
+        }
+        if self.alt_screen != other.alt_screen {
+            changes.push(Change::AltScreen(other.alt_screen));
+        }
+
+        changes
+    }
+
+    /// Apply changes from `diff` in order. Panics if a change doesn't fit on the screen.
+    pub fn apply(&mut self, changes: &[Change]) {
+        for change in changes {
+            match *change {
+                Change::Resize(size) => {
+                    self.size = size;
+                    self.cells = vec![Default::default(); size.width * size.height];
+                }
+                Change::MoveRect { ref dest, ref src } => {
+                    move_rect(&mut self.cells, self.size.width, dest, src);
+                }
+                Change::Row { y, ref cells } => {
+                    let start = y * self.size.width;
+                    self.cells[start..start + self.size.width].clone_from_slice(cells);
+                }
+                Change::Text { pos, ref cells } | Change::Attrs { pos, ref cells } => {
+                    let start = pos.y * self.size.width + pos.x;
+                    self.cells[start..start + cells.len()].clone_from_slice(cells);
+                }
+                Change::Cursor {
+                    pos,
+                    visible,
+                    ref shape,
+                } => {
+                    self.cursor_pos = pos;
+                    self.cursor_visible = visible;
+                    self.cursor_shape = shape.clone();
+                }
+                Change::Title(ref title) => self.title = title.clone(),
+                Change::AltScreen(alt_screen) => self.alt_screen = alt_screen,
+            }
+        }
+    }
+}
+
+/// One difference between two snapshots, as returned by `Snapshot::diff`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// The screen changed size. The old cells don't line up with the new ones, so every row
+    /// follows as a Row change.
+    Resize(Size),
+    /// Copy the cells in `src` to `dest`, as a scroll does. Like `MoveRectEvent`, the part of
+    /// `src` that `dest` doesn't cover keeps its old cells until later changes replace them.
+    MoveRect {
+        dest: Rect,
+        src: Rect,
+    },
+    /// Every cell on row `y`
+    Row {
+        y: usize,
+        cells: Vec<ScreenCell>,
+    },
+    /// A run of cells from `pos` along one row whose text changed. A single cell is a run of one.
+    Text {
+        pos: Pos,
+        cells: Vec<ScreenCell>,
+    },
+    /// A run of cells whose text is the same but whose attributes, colours or hyperlinks changed
+    Attrs {
+        pos: Pos,
+        cells: Vec<ScreenCell>,
+    },
+    Cursor {
+        pos: Pos,
+        visible: bool,
+        shape: CursorShape,
+    },
+    Title(String),
+    AltScreen(bool),
+}
+
+/// What changed in a cell between two snapshots
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CellChange {
+    Text,
+    Attrs,
+}
+
+fn cell_change(old: &ScreenCell, new: &ScreenCell) -> Option<CellChange> {
+    if old.chars != new.chars || old.width != new.width {
+        Some(CellChange::Text)
+    } else if old != new {
+        Some(CellChange::Attrs)
+    } else {
+        None
+    }
+}
+
+/// Push the changes for one row, as a whole row when more than half of it changed
+fn diff_row(old: &[ScreenCell], new: &[ScreenCell], y: usize, changes: &mut Vec<Change>) {
+    if old == new {
+        return;
+    }
+
+    let changed = old.iter().zip(new).filter(|&(a, b)| a != b).count();
+    if changed * 2 > new.len() {
+        changes.push(Change::Row {
+            y: y,
+            cells: new.to_vec(),
+        });
+        return;
+    }
+
+    let mut x = 0;
+    while x < new.len() {
+        let kind = match cell_change(&old[x], &new[x]) {
+            Some(kind) => kind,
+            None => {
+                x += 1;
+                continue;
+            }
+        };
+
+        let start = x;
+        while x < new.len() && cell_change(&old[x], &new[x]) == Some(kind) {
+            x += 1;
+        }
+
+        let pos = Pos::new(start, y);
+        let cells = new[start..x].to_vec();
+        changes.push(match kind {
+            CellChange::Text => Change::Text {
+                pos: pos,
+                cells: cells,
+            },
+            CellChange::Attrs => Change::Attrs {
+                pos: pos,
+                cells: cells,
+            },
+        });
+    }
+}
+
+/// Find the run of full width rows that, moved up or down together, fixes the most rows with
+/// text on them. Rows without text are cheap to redraw, so they don't count, and it takes at
+/// least two fixed rows to be worth a move. Returns the dest and src rects.
+fn find_scroll(old: &[ScreenCell], new: &[ScreenCell], size: Size) -> Option<(Rect, Rect)> {
+    let (width, height) = (size.width, size.height);
+    let old_rows: Vec<&[ScreenCell]> = old.chunks(width).collect();
+    let new_rows: Vec<&[ScreenCell]> = new.chunks(width).collect();
+    let has_text = |row: &[ScreenCell]| row.iter().any(|cell| !cell.chars.is_empty());
+
+    // (rows fixed, first dest row, rows in the run, src row minus dest row)
+    let mut best: Option<(usize, usize, usize, isize)> = None;
+    for distance in 1..height as isize {
+        for &shift in &[distance, -distance] {
+            let matches = |y: usize| {
+                let src = y as isize + shift;
+                src >= 0 && src < height as isize && new_rows[y] == old_rows[src as usize]
+            };
+
+            let mut y = 0;
+            while y < height {
+                if !matches(y) {
+                    y += 1;
+                    continue;
+                }
+
+                let start = y;
+                let mut fixed = 0;
+                while y < height && matches(y) {
+                    if new_rows[y] != old_rows[y] && has_text(new_rows[y]) {
+                        fixed += 1;
+                    }
+                    y += 1;
+                }
+
+                if fixed >= 2 && best.map_or(true, |(most, ..)| fixed > most) {
+                    best = Some((fixed, start, y - start, shift));
+                }
+            }
+        }
+    }
+
+    best.map(|(_, start, rows, shift)| {
+        let src_start = (start as isize + shift) as usize;
+        (
+            Rect::new(Pos::new(0, start), Size::new(width, rows)),
+            Rect::new(Pos::new(0, src_start), Size::new(width, rows)),
+        )
+    })
+}
+
+/// Copy the cells in `src` to `dest`, which is the same size
+fn move_rect(cells: &mut [ScreenCell], width: usize, dest: &Rect, src: &Rect) {
+    let moved: Vec<ScreenCell> = src
+        .positions()
+        .map(|pos| cells[pos.y * width + pos.x].clone())
+        .collect();
+    for (pos, cell) in dest.positions().zip(moved) {
+        cells[pos.y * width + pos.x] = cell;
+    }
+}
+
+impl VTerm {
+    /// Copy the whole screen, cursor and title into a Snapshot. The cells are read in one go
+    /// rather than a call into libvterm per cell.
+    pub fn snapshot(&self) -> Snapshot {
+        let size = self.get_size();
+        let state = unsafe { self.state_ptr.as_ref() };
+
+        let mut cursor_pos = ffi::VTermPos { row: 0, col: 0 };
+        unsafe { ffi::vterm_state_get_cursorpos(state, &mut cursor_pos) };
+        let cursor_shape =
+            CursorShape::try_from_i32(unsafe { ffi::vterm_state_get_cursor_shape(state) });
+
+        Snapshot {
+            size: size,
+            cells: self.get_cells_in_rect(&Rect::new(Pos::new(0, 0), size)),
+            cursor_pos: cursor_pos.as_pos(),
+            cursor_visible: int_to_bool(unsafe { ffi::vterm_state_get_cursor_visible(state) }),
+            cursor_shape: cursor_shape.unwrap_or(CursorShape::Block),
+            title: unsafe { self.callback_data.as_ref() }.title.clone(),
+            alt_screen: int_to_bool(unsafe { ffi::vterm_state_get_altscreen(state) }),
+        }
+    }
+}
@@ -119,43 +119,25 @@ impl VTerm {
         unsafe {
             ffi::vterm_state_set_default_colors(self.state_ptr.as_mut(), &fg_rgb, &bg_rgb);
         };
+
+        let palette = &mut unsafe { self.callback_data.as_mut() }.palette;
+        palette.default_fg = default_fg.clone();
+        palette.default_bg = default_bg.clone();
     }
 
     pub fn state_get_rgb_color_from_palette(&self, index: usize) -> ColorRGB {
-        let mut ffi_color: ffi::VTermColor = Default::default();
-        unsafe {
-            ffi::vterm_state_get_palette_color(
-                self.state_ptr.as_ref(),
-                index as c_int,
-                &mut ffi_color,
-            );
-        }
-        ColorRGB {
-            red: ffi_color.red,
-            green: ffi_color.green,
-            blue: ffi_color.blue,
-        }
+        get_rgb_color_from_palette(unsafe { self.state_ptr.as_ref() }, index)
     }
 
+    /// The lowest palette index with the given colour, or 0 if there isn't one
     pub fn state_get_palette_color_from_rgb(&self, target: &ColorRGB) -> usize {
-        for i in 0..256 {
-            let color = self.state_get_rgb_color_from_palette(i);
-            if color.red == target.red && color.green == target.green && color.blue == target.blue {
-                return i as usize;
-            }
-        }
-        0
+        let lookup = &unsafe { self.callback_data.as_ref() }.palette_lookup;
+        lookup.get(unsafe { self.state_ptr.as_ref() }, target)
     }
 
     /// move this to ffi classes since it deals with the ffi color type
     pub fn state_get_palette_color_from_c_rgb(&self, target: &ffi::VTermColor) -> usize {
-        for i in 0..256 {
-            let color = self.state_get_rgb_color_from_palette(i);
-            if color.red == target.red && color.green == target.green && color.blue == target.blue {
-                return i as usize;
-            }
-        }
-        0
+        self.state_get_palette_color_from_rgb(&target.as_color_rgb())
     }
 
     pub fn state_reset(&mut self, hard: bool) {
@@ -224,25 +206,76 @@ impl VTerm {
             None
         };
 
-        self.state_callbacks = Some(callbacks);
-
         if self.state_event_tx.is_none() {
             let (tx, rx) = mpsc::channel();
             self.state_event_tx = Some(tx);
             self.state_event_rx = Some(rx);
         }
 
+        let handler = Box::new(self.state_event_tx.clone().unwrap());
+        self.set_state_callbacks(callbacks, handler);
+    }
+
+    /// Send every state callback to the given handler instead of a channel. The handler is
+    /// called synchronously from `write`, and what it returns goes straight back to libvterm.
+    ///
+    /// This replaces the callbacks the screen layer relies on, the same as
+    /// `state_receive_events` does. The title is still kept for snapshots.
+    pub fn set_state_handler(&mut self, handler: Box<dyn StateHandler>) {
+        let callbacks = ffi::VTermStateCallbacks {
+            put_glyph: Some(state_callbacks::put_glyph),
+            move_cursor: Some(state_callbacks::move_cursor),
+            scroll_rect: Some(state_callbacks::scroll_rect),
+            move_rect: Some(state_callbacks::move_rect),
+            erase: Some(state_callbacks::erase),
+            init_pen: Some(state_callbacks::init_pen),
+            set_pen_attr: Some(state_callbacks::set_pen_attr),
+            set_term_prop: Some(state_callbacks::set_term_prop),
+            bell: Some(state_callbacks::bell),
+            resize: Some(state_callbacks::resize),
+            set_line_info: Some(state_callbacks::set_line_info),
+        };
+
+        // Dropping our sender lets anyone still holding the receiver see the channel close
+        self.state_event_tx = None;
+        self.set_state_callbacks(callbacks, handler);
+    }
+
+    fn set_state_callbacks(
+        &mut self,
+        callbacks: ffi::VTermStateCallbacks,
+        handler: Box<dyn StateHandler>,
+    ) {
+        let data = unsafe { self.callback_data.as_mut() };
+        data.state_callbacks = Some(callbacks);
+        data.state_handler = Some(handler);
+
+        // The screen layer no longer sees set_term_prop, so keep the title here instead
+        let mut registered = callbacks;
+        registered.set_term_prop = Some(state_callbacks::set_term_prop);
+        data.registered_state_callbacks = registered;
+
         unsafe {
-            let self_ptr: *mut c_void = self as *mut _ as *mut c_void;
+            let data_ptr: *mut c_void = self.callback_data.as_ptr() as *mut c_void;
             ffi::vterm_state_set_callbacks(
                 self.state_ptr.as_mut(),
-                self.state_callbacks.as_ref().unwrap(),
-                self_ptr,
+                &self.callback_data.as_ref().registered_state_callbacks,
+                data_ptr,
             );
         }
     }
 }
 
+// These work on the state pointer alone so callbacks, which don't have the VTerm, can use them.
+
+pub(crate) fn get_rgb_color_from_palette(state: &ffi::VTermState, index: usize) -> ColorRGB {
+    let mut ffi_color: ffi::VTermColor = Default::default();
+    unsafe {
+        ffi::vterm_state_get_palette_color(state, index as c_int, &mut ffi_color);
+    }
+    ffi_color.as_color_rgb()
+}
+
 mod tests {
     #![allow(unused_imports)]
     use super::super::*;
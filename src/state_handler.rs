@@ -0,0 +1,162 @@
+#![allow(unused_variables)]
+
+use std::sync::mpsc;
+
+use super::*;
+
+/// Receives state callbacks from libvterm as they happen. Every method has a no-op default, so
+/// implement only the ones you care about. Methods that return a value hand it straight back to
+/// libvterm, and the defaults behave as if the callback wasn't registered at all.
+///
+/// Register one with `VTerm::set_state_handler`. Note the screen layer is itself built on these
+/// callbacks, so a state handler takes the place of the screen and screen handlers stop getting
+/// most events.
+pub trait StateHandler {
+    fn put_glyph(&mut self, glyph_info: GlyphInfo, pos: Pos) {}
+
+    fn move_cursor(&mut self, new: Pos, old: Pos, is_visible: bool) {}
+
+    /// Return true if the scroll was handled. Otherwise libvterm falls back to calling
+    /// `move_rect` and `erase`.
+    fn scroll_rect(&mut self, rect: Rect, downward: isize, rightward: isize) -> bool {
+        false
+    }
+
+    fn move_rect(&mut self, dest: Rect, src: Rect) {}
+
+    /// `is_selective` is true when DECSED was used and false if ED was used
+    fn erase(&mut self, rect: Rect, is_selective: bool) {}
+
+    fn init_pen(&mut self) {}
+
+    fn set_pen_attr(&mut self, attr: PenAttr) {}
+
+    /// Return false to reject the change. libvterm then doesn't store the new value.
+    fn set_term_prop(&mut self, prop: TermProp) -> bool {
+        true
+    }
+
+    fn bell(&mut self) {}
+
+    fn resize(&mut self, size: Size) {}
+
+    /// Return false to keep the old line info for the row.
+    fn set_line_info(&mut self, row: usize, new: LineInfo, old: LineInfo) -> bool {
+        true
+    }
+}
+
+/// The channel api. Each callback is turned into a StateEvent and sent.
+impl StateHandler for mpsc::Sender<StateEvent> {
+    fn put_glyph(&mut self, glyph_info: GlyphInfo, pos: Pos) {
+        let _ = self.send(StateEvent::PutGlyph(PutGlyphEvent {
+            glyph_info: glyph_info,
+            pos: pos,
+        }));
+    }
+
+    fn move_cursor(&mut self, new: Pos, old: Pos, is_visible: bool) {
+        let _ = self.send(StateEvent::MoveCursor(MoveCursorEvent {
+            new: new,
+            old: old,
+            is_visible: is_visible,
+        }));
+    }
+
+    fn scroll_rect(&mut self, rect: Rect, downward: isize, rightward: isize) -> bool {
+        self.send(StateEvent::ScrollRect(ScrollRectEvent {
+            rect: rect,
+            downward: downward,
+            rightward: rightward,
+        }))
+        .is_ok()
+    }
+
+    fn move_rect(&mut self, dest: Rect, src: Rect) {
+        let _ = self.send(StateEvent::MoveRect(MoveRectEvent {
+            dest: dest,
+            src: src,
+        }));
+    }
+
+    fn erase(&mut self, rect: Rect, is_selective: bool) {
+        let _ = self.send(StateEvent::Erase(EraseEvent {
+            rect: rect,
+            is_selective: is_selective,
+        }));
+    }
+
+    fn init_pen(&mut self) {
+        let _ = self.send(StateEvent::InitPen(InitPenEvent));
+    }
+
+    fn set_pen_attr(&mut self, attr: PenAttr) {
+        let event = match attr {
+            PenAttr::Background {
+                rgb,
+                palette,
+                color,
+            } => StateEvent::PenBackground(PenBackgroundEvent {
+                rgb: rgb,
+                palette: palette,
+                color: color,
+            }),
+            PenAttr::Blink(is_on) => StateEvent::PenBlink(PenBlinkEvent { is_on: is_on }),
+            PenAttr::Bold(is_on) => StateEvent::PenBold(PenBoldEvent { is_on: is_on }),
+            PenAttr::Font(font) => StateEvent::PenFont(PenFontEvent { font: font }),
+            PenAttr::Foreground {
+                rgb,
+                palette,
+                color,
+            } => StateEvent::PenForeground(PenForegroundEvent {
+                rgb: rgb,
+                palette: palette,
+                color: color,
+            }),
+            PenAttr::Italic(is_on) => StateEvent::PenItalic(PenItalicEvent { is_on: is_on }),
+            PenAttr::Reverse(is_on) => StateEvent::PenReverse(PenReverseEvent { is_on: is_on }),
+            PenAttr::Strike(is_on) => StateEvent::PenStrike(PenStrikeEvent { is_on: is_on }),
+            PenAttr::Underline(mode) => StateEvent::PenUnderline(PenUnderlineEvent { mode: mode }),
+        };
+
+        let _ = self.send(event);
+    }
+
+    fn set_term_prop(&mut self, prop: TermProp) -> bool {
+        let event = match prop {
+            TermProp::AltScreen(is_on) => StateEvent::AltScreen(AltScreenEvent { is_on: is_on }),
+            TermProp::CursorBlink(is_on) => {
+                StateEvent::CursorBlink(CursorBlinkEvent { is_on: is_on })
+            }
+            TermProp::CursorShape(shape) => {
+                StateEvent::CursorShape(CursorShapeEvent { shape: shape })
+            }
+            TermProp::CursorVisible(is_on) => {
+                StateEvent::CursorVisible(CursorVisibleEvent { is_on: is_on })
+            }
+            TermProp::IconName(name) => StateEvent::IconName(IconNameEvent { name: name }),
+            TermProp::Mouse(mode) => StateEvent::Mouse(MouseEvent { mode: mode }),
+            TermProp::Reverse(is_on) => StateEvent::Reverse(ReverseEvent { is_on: is_on }),
+            TermProp::Title(title) => StateEvent::Title(TitleEvent { title: title }),
+        };
+
+        self.send(event).is_ok()
+    }
+
+    fn bell(&mut self) {
+        let _ = self.send(StateEvent::Bell(BellEvent));
+    }
+
+    fn resize(&mut self, size: Size) {
+        let _ = self.send(StateEvent::Resize(ResizeEvent { size: size }));
+    }
+
+    fn set_line_info(&mut self, row: usize, new: LineInfo, old: LineInfo) -> bool {
+        self.send(StateEvent::LineInfo(LineInfoEvent {
+            row: row as i32,
+            new: new,
+            old: old,
+        }))
+        .is_ok()
+    }
+}
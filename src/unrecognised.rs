@@ -0,0 +1,212 @@
+use libc::c_void;
+
+use super::*;
+
+/// A CSI, OSC or DCS sequence the state layer didn't recognise
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sequence {
+    Csi {
+        leader: Vec<u8>,
+        args: Vec<CsiArg>,
+        intermed: Vec<u8>,
+        command: u8,
+    },
+    Osc {
+        /// The number before the first `;`, like 52 in `OSC 52;c;aGk= ST`. None when the
+        /// string doesn't start with one.
+        command: Option<u32>,
+        /// Everything after the first `;`, or the whole string when there was no number
+        data: Vec<u8>,
+    },
+    /// Split the same way as a CSI, so XTGETTCAP's `DCS + q 544e ST` has an intermed of `+`, a
+    /// command of `q` and data of `544e`.
+    Dcs {
+        leader: Vec<u8>,
+        args: Vec<CsiArg>,
+        intermed: Vec<u8>,
+        /// Zero when the string ended before a final byte
+        command: u8,
+        data: Vec<u8>,
+    },
+}
+
+impl Sequence {
+    pub(crate) fn osc_from_bytes(bytes: &[u8]) -> Sequence {
+        let digits = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+        let command = ::std::str::from_utf8(&bytes[..digits])
+            .ok()
+            .and_then(|digits| digits.parse().ok());
+
+        match (command, bytes.get(digits)) {
+            (Some(command), Some(b';')) => Sequence::Osc {
+                command: Some(command),
+                data: bytes[digits + 1..].to_vec(),
+            },
+            (Some(command), None) => Sequence::Osc {
+                command: Some(command),
+                data: vec![],
+            },
+            _ => Sequence::Osc {
+                command: None,
+                data: bytes.to_vec(),
+            },
+        }
+    }
+
+    pub(crate) fn dcs_from_bytes(bytes: &[u8]) -> Sequence {
+        let leader_len = bytes
+            .iter()
+            .take_while(|b| (0x3c..=0x3f).contains(*b))
+            .count();
+        let (leader, rest) = bytes.split_at(leader_len);
+
+        let params_len = rest
+            .iter()
+            .take_while(|&&b| b.is_ascii_digit() || b == b';' || b == b':')
+            .count();
+        let (params, rest) = rest.split_at(params_len);
+
+        let intermed_len = rest.iter().take_while(|&&b| b & 0xf0 == 0x20).count();
+        let (intermed, rest) = rest.split_at(intermed_len);
+
+        let (command, data) = match rest.first() {
+            Some(&b) if (0x40..=0x7e).contains(&b) => (b, &rest[1..]),
+            _ => (0, rest),
+        };
+
+        Sequence::Dcs {
+            leader: leader.to_vec(),
+            args: parse_args(params),
+            intermed: intermed.to_vec(),
+            command: command,
+            data: data.to_vec(),
+        }
+    }
+}
+
+/// Parse `;` and `:` separated numbers the way libvterm does for CSI, so there is always at
+/// least one argument.
+fn parse_args(params: &[u8]) -> Vec<CsiArg> {
+    let mut args = vec![CsiArg {
+        value: None,
+        has_more: false,
+    }];
+
+    for &b in params {
+        match b {
+            b';' | b':' => {
+                args.last_mut().unwrap().has_more = b == b':';
+                args.push(CsiArg {
+                    value: None,
+                    has_more: false,
+                });
+            }
+            _ => {
+                let arg = args.last_mut().unwrap();
+                let value = arg.value.unwrap_or(0);
+                arg.value = Some(value.saturating_mul(10).saturating_add((b - b'0') as u32));
+            }
+        }
+    }
+
+    args
+}
+
+impl VTerm {
+    /// Pass the CSI, OSC and DCS sequences the state layer doesn't recognise to the given
+    /// handler. A closure taking `(&Sequence, &mut Vec<u8>)` works as a handler.
+    ///
+    /// This sits below the screen and state layers, so it works alongside their callbacks but not
+    /// when a parser handler has taken the parser.
+    pub fn set_unrecognised_handler(&mut self, handler: Box<dyn UnrecognisedHandler>) {
+        unsafe { self.callback_data.as_mut() }.unrecognised_handler = Some(handler);
+    }
+
+    /// Stop passing unrecognised sequences on. They are ignored again.
+    pub fn clear_unrecognised_handler(&mut self) {
+        unsafe { self.callback_data.as_mut() }.unrecognised_handler = None;
+    }
+
+    /// Point the state's fallbacks at our callbacks. They stay registered and do nothing without a
+    /// handler.
+    pub(crate) fn register_unrecognised_callbacks(&mut self) {
+        unsafe {
+            let data_ptr: *mut c_void = self.callback_data.as_ptr() as *mut c_void;
+            ffi::vterm_state_set_unrecognised_fallbacks(
+                self.state_ptr.as_mut(),
+                &self.callback_data.as_ref().unrecognised_callbacks,
+                data_ptr,
+            );
+        }
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::super::*;
+
+    #[test]
+    fn osc_from_bytes_splits_off_the_command_number() {
+        assert_eq!(
+            Sequence::osc_from_bytes(b"52;c;aGk="),
+            Sequence::Osc {
+                command: Some(52),
+                data: b"c;aGk=".to_vec(),
+            }
+        );
+        assert_eq!(
+            Sequence::osc_from_bytes(b"104"),
+            Sequence::Osc {
+                command: Some(104),
+                data: vec![],
+            }
+        );
+        assert_eq!(
+            Sequence::osc_from_bytes(b"foo;bar"),
+            Sequence::Osc {
+                command: None,
+                data: b"foo;bar".to_vec(),
+            }
+        );
+    }
+
+    #[test]
+    fn dcs_from_bytes_splits_like_csi() {
+        assert_eq!(
+            Sequence::dcs_from_bytes(b"+q544e"),
+            Sequence::Dcs {
+                leader: vec![],
+                args: vec![CsiArg {
+                    value: None,
+                    has_more: false,
+                }],
+                intermed: b"+".to_vec(),
+                command: b'q',
+                data: b"544e".to_vec(),
+            }
+        );
+        assert_eq!(
+            Sequence::dcs_from_bytes(b"0;1:2q#0"),
+            Sequence::Dcs {
+                leader: vec![],
+                args: vec![
+                    CsiArg {
+                        value: Some(0),
+                        has_more: false,
+                    },
+                    CsiArg {
+                        value: Some(1),
+                        has_more: true,
+                    },
+                    CsiArg {
+                        value: Some(2),
+                        has_more: false,
+                    },
+                ],
+                intermed: vec![],
+                command: b'q',
+                data: b"#0".to_vec(),
+            }
+        );
+    }
+}
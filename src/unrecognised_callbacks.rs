@@ -0,0 +1,112 @@
+use libc::{c_char, c_int, c_long, c_void, size_t};
+use std::slice;
+
+use super::parser_callbacks::{as_bytes, c_str_bytes};
+use super::*;
+
+pub extern "C" fn csi(
+    leader: *const c_char,
+    args: *const c_long,
+    argcount: c_int,
+    intermed: *const c_char,
+    command: c_char,
+    data: *mut c_void,
+) -> c_int {
+    let sequence = Sequence::Csi {
+        leader: unsafe { c_str_bytes(leader) }.to_vec(),
+        args: unsafe { slice::from_raw_parts(args, argcount as usize) }
+            .iter()
+            .map(|&arg| CsiArg::from_raw(arg))
+            .collect(),
+        intermed: unsafe { c_str_bytes(intermed) }.to_vec(),
+        command: command as u8,
+    };
+
+    handle(data, &sequence)
+}
+
+pub extern "C" fn osc(command: *const c_char, cmdlen: size_t, data: *mut c_void) -> c_int {
+    let sequence = Sequence::osc_from_bytes(unsafe { as_bytes(command, cmdlen) });
+
+    let data_ptr = data;
+    let data: &mut CallbackData = unsafe { &mut *(data as *mut CallbackData) };
+    match sequence {
+        // Hyperlinks are tracked by the crate, so the handler doesn't see them
+        Sequence::Osc {
+            command: Some(8),
+            data: ref osc_data,
+        } => {
+            let screen = unsafe { &*ffi::vterm_obtain_screen(data.vterm_ptr.as_ptr()) };
+            data.hyperlinks
+                .handle_osc8(unsafe { data.state_ptr.as_mut() }, screen, osc_data);
+            1
+        }
+        Sequence::Osc {
+            command: Some(52),
+            data: ref osc_data,
+        } if data.clipboard_policy != ClipboardPolicy::none() => {
+            let mut response = vec![];
+            handle_osc52(
+                data.clipboard.as_mut(),
+                &data.clipboard_policy,
+                osc_data,
+                &mut response,
+            );
+            push_output(data, &response);
+            1
+        }
+        Sequence::Osc {
+            command: Some(command @ (4 | 10 | 11 | 104 | 110 | 111)),
+            data: ref osc_data,
+        } => {
+            let mut response = vec![];
+            handle_osc_color(
+                unsafe { data.state_ptr.as_mut() },
+                &data.palette,
+                command,
+                osc_data,
+                &mut response,
+            );
+            data.palette_lookup.invalidate();
+            push_output(data, &response);
+            1
+        }
+        _ => handle(data_ptr, &sequence),
+    }
+}
+
+pub extern "C" fn dcs(command: *const c_char, cmdlen: size_t, data: *mut c_void) -> c_int {
+    handle(
+        data,
+        &Sequence::dcs_from_bytes(unsafe { as_bytes(command, cmdlen) }),
+    )
+}
+
+/// Give the sequence to the handler, if there is one, and push out any response it wrote.
+fn handle(data: *mut c_void, sequence: &Sequence) -> c_int {
+    let data: &mut CallbackData = unsafe { &mut *(data as *mut CallbackData) };
+    let handler = match data.unrecognised_handler.as_mut() {
+        Some(handler) => handler,
+        None => return 0,
+    };
+
+    let mut response = vec![];
+    let is_handled = handler.handle(sequence, &mut response);
+    push_output(data, &response);
+
+    bool_to_int(is_handled)
+}
+
+fn push_output(data: &mut CallbackData, bytes: &[u8]) {
+    if bytes.is_empty() {
+        return;
+    }
+
+    unsafe {
+        ffi::vterm_push_output_bytes(
+            data.vterm_ptr.as_mut(),
+            bytes.as_ptr() as *const c_char,
+            bytes.len(),
+        );
+    }
+}
@@ -0,0 +1,18 @@
+use super::*;
+
+/// Receives the CSI, OSC and DCS sequences libvterm doesn't handle itself, like OSC 7 or
+/// XTGETTCAP. Register one with `VTerm::set_unrecognised_handler`.
+pub trait UnrecognisedHandler {
+    /// Return true if the sequence was handled. Bytes written to `response` are put in the output
+    /// buffer, for answering queries.
+    fn handle(&mut self, sequence: &Sequence, response: &mut Vec<u8>) -> bool;
+}
+
+impl<F> UnrecognisedHandler for F
+where
+    F: FnMut(&Sequence, &mut Vec<u8>) -> bool,
+{
+    fn handle(&mut self, sequence: &Sequence, response: &mut Vec<u8>) -> bool {
+        self(sequence, response)
+    }
+}
@@ -1,4 +1,4 @@
-use libc::{c_int, size_t};
+use libc::{c_char, c_int, c_void, size_t};
 use std::io::prelude::*;
 use std::ptr::NonNull;
 use std::sync::mpsc;
@@ -8,41 +8,129 @@ use super::*;
 pub struct VTerm {
     pub ptr: NonNull<ffi::VTerm>,
 
-    pub screen_callbacks: Option<ffi::VTermScreenCallbacks>,
     pub screen_event_rx: Option<mpsc::Receiver<ScreenEvent>>,
-    pub screen_event_tx: Option<mpsc::Sender<ScreenEvent>>,
+    pub(crate) screen_event_tx: Option<mpsc::Sender<ScreenEvent>>,
     pub screen_ptr: NonNull<ffi::VTermScreen>,
 
-    pub state_callbacks: Option<ffi::VTermStateCallbacks>,
     pub state_event_rx: Option<mpsc::Receiver<StateEvent>>,
-    pub state_event_tx: Option<mpsc::Sender<StateEvent>>,
+    pub(crate) state_event_tx: Option<mpsc::Sender<StateEvent>>,
     pub state_ptr: NonNull<ffi::VTermState>,
+
+    pub parser_event_rx: Option<mpsc::Receiver<ParserEvent>>,
+    pub(crate) parser_event_tx: Option<mpsc::Sender<ParserEvent>>,
+
+    /// Heap allocated so its address, which libvterm holds on to as callback user data, stays the
+    /// same when the VTerm is moved.
+    pub(crate) callback_data: NonNull<CallbackData>,
+}
+
+/// Everything the screen, state and parser callbacks need. libvterm keeps pointers to this and to
+/// the callback structs inside it.
+pub(crate) struct CallbackData {
+    pub vterm_ptr: NonNull<ffi::VTerm>,
+    pub state_ptr: NonNull<ffi::VTermState>,
+
+    /// The callbacks the screen handler asked for
+    pub screen_callbacks: Option<ffi::VTermScreenCallbacks>,
+    /// What libvterm was given, which adds the scrollback callbacks when scrollback is enabled
+    pub registered_screen_callbacks: ffi::VTermScreenCallbacks,
+    pub screen_handler: Option<Box<dyn ScreenHandler>>,
+    pub scrollback: Option<Scrollback>,
+
+    /// The callbacks the state handler asked for
+    pub state_callbacks: Option<ffi::VTermStateCallbacks>,
+    /// What libvterm was given, which adds set_term_prop to keep the title
+    pub registered_state_callbacks: ffi::VTermStateCallbacks,
+    pub state_handler: Option<Box<dyn StateHandler>>,
+
+    /// The state layer's parser callbacks and their user data, as they were before a parser
+    /// handler replaced them
+    pub layer_parser: (*const ffi::VTermParserCallbacks, *mut c_void),
+    pub parser_callbacks: Option<ffi::VTermParserCallbacks>,
+    pub parser_handler: Option<Box<dyn ParserHandler>>,
+
+    pub unrecognised_callbacks: ffi::VTermParserCallbacks,
+    pub unrecognised_handler: Option<Box<dyn UnrecognisedHandler>>,
+
+    pub hyperlinks: Hyperlinks,
+
+    pub clipboard: Box<dyn Clipboard>,
+    pub clipboard_policy: ClipboardPolicy,
+
+    /// The palette the embedder set, which OSC 104, 110 and 111 reset to
+    pub palette: Palette,
+    pub palette_lookup: PaletteLookup,
+
+    /// The last title set with OSC 0 or 2, which libvterm passes on without keeping
+    pub title: String,
 }
 
 impl VTerm {
     /// Attempt to create a new VTerm of the given size.
-    pub fn new(size: &Size) -> Option<VTerm> {
+    pub fn new(size: &Size) -> Result<VTerm, Error> {
         let mut vterm_ptr =
-            unsafe { NonNull::new(ffi::vterm_new(size.height as c_int, size.width as c_int))? };
-        let screen_ptr = unsafe { NonNull::new(ffi::vterm_obtain_screen(vterm_ptr.as_mut()))? };
-        let state_ptr = unsafe { NonNull::new(ffi::vterm_obtain_state(vterm_ptr.as_mut()))? };
+            unsafe { NonNull::new(ffi::vterm_new(size.height as c_int, size.width as c_int)) }
+                .ok_or(Error::AllocationFailed)?;
+        let screen_ptr = unsafe { NonNull::new(ffi::vterm_obtain_screen(vterm_ptr.as_mut())) }
+            .ok_or(Error::AllocationFailed)?;
+        let state_ptr = unsafe { NonNull::new(ffi::vterm_obtain_state(vterm_ptr.as_mut())) }
+            .ok_or(Error::AllocationFailed)?;
+        let layer_parser = unsafe {
+            (
+                ffi::vterm_parser_get_callbacks(vterm_ptr.as_mut()),
+                ffi::vterm_parser_get_cbdata(vterm_ptr.as_mut()),
+            )
+        };
+
+        let callback_data = Box::new(CallbackData {
+            vterm_ptr: vterm_ptr,
+            state_ptr: state_ptr,
+            screen_callbacks: None,
+            registered_screen_callbacks: Default::default(),
+            screen_handler: None,
+            scrollback: None,
+            state_callbacks: None,
+            registered_state_callbacks: Default::default(),
+            state_handler: None,
+            layer_parser: layer_parser,
+            parser_callbacks: None,
+            parser_handler: None,
+            unrecognised_callbacks: ffi::VTermParserCallbacks {
+                csi: Some(unrecognised_callbacks::csi),
+                osc: Some(unrecognised_callbacks::osc),
+                dcs: Some(unrecognised_callbacks::dcs),
+                ..Default::default()
+            },
+            unrecognised_handler: None,
+            hyperlinks: Default::default(),
+            clipboard: Box::new(MemoryClipboard::new()),
+            clipboard_policy: ClipboardPolicy::none(),
+            palette: Default::default(),
+            palette_lookup: Default::default(),
+            title: String::new(),
+        });
 
         let mut vterm = VTerm {
             ptr: vterm_ptr,
-            screen_callbacks: None,
             screen_event_rx: None,
             screen_event_tx: None,
             screen_ptr: screen_ptr,
 
-            state_callbacks: None,
             state_event_rx: None,
             state_event_tx: None,
             state_ptr: state_ptr,
+
+            parser_event_rx: None,
+            parser_event_tx: None,
+
+            callback_data: unsafe { NonNull::new_unchecked(Box::into_raw(callback_data)) },
         };
 
+        vterm.register_unrecognised_callbacks();
+        vterm.register_screen_callbacks();
         vterm.screen_reset(true);
 
-        Some(vterm)
+        Ok(vterm)
     }
 
     pub fn get_size(&self) -> Size {
@@ -70,6 +158,38 @@ impl VTerm {
     pub fn set_utf8(&mut self, is_utf8: bool) {
         unsafe { ffi::vterm_set_utf8(self.ptr.as_mut(), super::bool_to_int(is_utf8)) }
     }
+
+    /// Returns the number of bytes waiting in the output buffer. These are bytes meant for the
+    /// program running in the terminal, like encoded key presses and replies to queries.
+    pub fn output_pending(&self) -> usize {
+        unsafe { ffi::vterm_output_get_buffer_current(self.ptr.as_ref()) as usize }
+    }
+
+    /// Returns the current capacity of the output buffer. It starts small and grows to fit output
+    /// that wouldn't otherwise fit, so this is how large it has had to get, not a limit.
+    pub fn output_buffer_size(&self) -> usize {
+        unsafe { ffi::vterm_output_get_buffer_size(self.ptr.as_ref()) as usize }
+    }
+
+    /// Returns how many more bytes the output buffer can hold.
+    pub fn output_buffer_remaining(&self) -> usize {
+        unsafe { ffi::vterm_output_get_buffer_remaining(self.ptr.as_ref()) as usize }
+    }
+}
+
+/// Reads from the output buffer. A read returns `Ok(0)` when nothing is pending, so
+/// `io::copy` can be used to drain it.
+impl Read for VTerm {
+    fn read(&mut self, buf: &mut [u8]) -> ::std::io::Result<usize> {
+        let size = unsafe {
+            ffi::vterm_output_read(
+                self.ptr.as_mut(),
+                buf.as_mut_ptr() as *mut c_char,
+                buf.len() as size_t,
+            ) as usize
+        };
+        Ok(size)
+    }
 }
 
 impl Write for VTerm {
@@ -88,7 +208,10 @@ impl Write for VTerm {
 
 impl Drop for VTerm {
     fn drop(&mut self) {
-        unsafe { ffi::vterm_free(self.ptr.as_mut()) }
+        unsafe {
+            ffi::vterm_free(self.ptr.as_mut());
+            drop(Box::from_raw(self.callback_data.as_ptr()));
+        }
     }
 }
 
@@ -159,4 +282,42 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(4, result.unwrap());
     }
+
+    #[test]
+    fn vterm_can_read() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 2,
+        })
+        .unwrap();
+        assert_eq!(0, vterm.output_pending());
+
+        // DSR cursor position report
+        vterm.write(b"\x1b[6n").unwrap();
+        assert_eq!(6, vterm.output_pending());
+
+        let mut output = vec![];
+        ::std::io::copy(&mut vterm, &mut output).unwrap();
+        assert_eq!(output, b"\x1b[1;1R");
+        assert_eq!(0, vterm.output_pending());
+    }
+
+    #[test]
+    fn vterm_grows_the_output_buffer_for_replies() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 2,
+        })
+        .unwrap();
+        let size = vterm.output_buffer_size();
+
+        // DSR cursor position reports and DECRQSS for SGR, unread
+        let queries = b"\x1b[6n\x1bP$qm\x1b\\".repeat(size);
+        vterm.write(&queries).unwrap();
+        assert!(vterm.output_buffer_size() > size);
+
+        let mut output = vec![];
+        ::std::io::copy(&mut vterm, &mut output).unwrap();
+        assert_eq!(output, b"\x1b[1;1R\x1bP1$rm\x1b\\".repeat(size));
+    }
 }
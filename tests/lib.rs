@@ -1,4 +1,5 @@
 extern crate libc;
+extern crate proptest;
 extern crate regex;
 extern crate term;
 extern crate vterm_sys;
@@ -0,0 +1,129 @@
+use std::cell::RefCell;
+use std::io::prelude::*;
+use std::rc::Rc;
+use vterm_sys::*;
+
+fn new_vterm() -> VTerm {
+    VTerm::new(&Size {
+        height: 2,
+        width: 10,
+    })
+    .unwrap()
+}
+
+fn read_output(vterm: &mut VTerm) -> Vec<u8> {
+    let mut output = vec![];
+    ::std::io::copy(vterm, &mut output).unwrap();
+    output
+}
+
+#[test]
+fn clipboard_is_set_by_osc52() {
+    let mut vterm = new_vterm();
+    vterm.set_clipboard_policy(ClipboardPolicy::all());
+
+    vterm.write(b"\x1b]52;c;aGk=\x07").unwrap();
+
+    assert_eq!(
+        vterm.clipboard_mut().get(Selection::Clipboard),
+        Some(b"hi".to_vec())
+    );
+    assert_eq!(read_output(&mut vterm), vec![]);
+}
+
+#[test]
+fn clipboard_answers_osc52_queries() {
+    let mut vterm = new_vterm();
+    vterm.set_clipboard_policy(ClipboardPolicy::all());
+    vterm
+        .clipboard_mut()
+        .set(Selection::Primary, "héllo".as_bytes().to_vec());
+
+    vterm.write(b"\x1b]52;p;?\x1b\\").unwrap();
+
+    assert_eq!(
+        read_output(&mut vterm),
+        b"\x1b]52;p;aMOpbGxv\x1b\\".to_vec()
+    );
+}
+
+#[test]
+fn clipboard_policy_can_deny_reads() {
+    let mut vterm = new_vterm();
+    vterm.set_clipboard_policy(ClipboardPolicy {
+        allow_write: true,
+        allow_read: false,
+    });
+    let sequences = Rc::new(RefCell::new(vec![]));
+    let seen = sequences.clone();
+    vterm.set_unrecognised_handler(Box::new(move |sequence: &Sequence, _: &mut Vec<u8>| {
+        seen.borrow_mut().push(sequence.clone());
+        true
+    }));
+
+    vterm.write(b"\x1b]52;c;aGk=\x07\x1b]52;c;?\x07").unwrap();
+
+    assert_eq!(read_output(&mut vterm), vec![]);
+    assert_eq!(*sequences.borrow(), vec![]);
+    assert_eq!(
+        vterm.clipboard_mut().get(Selection::Clipboard),
+        Some(b"hi".to_vec())
+    );
+}
+
+#[test]
+fn clipboard_is_left_to_the_unrecognised_handler_by_default() {
+    let mut vterm = new_vterm();
+    let sequences = Rc::new(RefCell::new(vec![]));
+    let seen = sequences.clone();
+    vterm.set_unrecognised_handler(Box::new(move |sequence: &Sequence, _: &mut Vec<u8>| {
+        seen.borrow_mut().push(sequence.clone());
+        true
+    }));
+
+    vterm.write(b"\x1b]52;c;aGk=\x07").unwrap();
+
+    assert_eq!(vterm.clipboard_policy(), &ClipboardPolicy::none());
+    assert_eq!(
+        *sequences.borrow(),
+        vec![Sequence::Osc {
+            command: Some(52),
+            data: b"c;aGk=".to_vec(),
+        }]
+    );
+    assert_eq!(vterm.clipboard_mut().get(Selection::Clipboard), None);
+}
+
+#[test]
+fn clipboard_answers_queries_longer_than_the_output_buffer() {
+    let mut vterm = new_vterm();
+    vterm.set_clipboard_policy(ClipboardPolicy::all());
+    vterm
+        .clipboard_mut()
+        .set(Selection::Clipboard, vec![0; 300]);
+
+    vterm.write(b"\x1b]52;c;?\x07").unwrap();
+
+    let output = read_output(&mut vterm);
+    assert_eq!(output.len(), "\x1b]52;c;".len() + 400 + "\x1b\\".len());
+    assert!(output.ends_with(b"AAAA\x1b\\"));
+}
+
+#[test]
+fn clipboard_is_set_by_osc52_split_over_writes() {
+    let mut vterm = new_vterm();
+    vterm.set_clipboard_policy(ClipboardPolicy::all());
+
+    // "hi!" is "aGkh" in base64, so this is 300 bytes of data
+    let mut osc = b"\x1b]52;c;".to_vec();
+    osc.extend(b"aGkh".repeat(100));
+    osc.extend(b"\x1b\\");
+    for chunk in osc.chunks(37) {
+        vterm.write(chunk).unwrap();
+    }
+
+    assert_eq!(
+        vterm.clipboard_mut().get(Selection::Clipboard),
+        Some(b"hi!".repeat(100))
+    );
+}
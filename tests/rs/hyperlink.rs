@@ -0,0 +1,158 @@
+use std::cell::RefCell;
+use std::io::prelude::*;
+use std::rc::Rc;
+use vterm_sys::*;
+
+fn link(uri: &str, id: Option<&str>) -> Hyperlink {
+    Hyperlink {
+        uri: uri.to_string(),
+        id: id.map(|id| id.to_string()),
+    }
+}
+
+fn row(vterm: &VTerm, y: usize) -> Rect {
+    Rect::new(Pos::new(0, y), Size::new(vterm.get_size().width, 1))
+}
+
+#[test]
+fn hyperlink_is_recorded_on_cells_written_while_open() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 10,
+    })
+    .unwrap();
+
+    vterm
+        .write(b"a\x1b]8;id=x;http://example.com\x1b\\link\x1b]8;;\x1b\\b")
+        .unwrap();
+
+    let expected = link("http://example.com", Some("x"));
+    assert_eq!(vterm.screen_get_cell(&Pos::new(0, 0)).hyperlink, None);
+    assert_eq!(
+        vterm.screen_get_cell(&Pos::new(1, 0)).hyperlink,
+        Some(expected.clone())
+    );
+    assert_eq!(
+        vterm.screen_get_cell(&Pos::new(4, 0)).hyperlink,
+        Some(expected.clone())
+    );
+    assert_eq!(vterm.screen_get_cell(&Pos::new(5, 0)).hyperlink, None);
+
+    assert_eq!(
+        vterm.screen_get_hyperlinks(&row(&vterm, 0)),
+        vec![HyperlinkSpan {
+            hyperlink: expected,
+            rect: Rect::new(Pos::new(1, 0), Size::new(4, 1)),
+        }]
+    );
+}
+
+#[test]
+fn hyperlink_uri_can_be_split_over_writes() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 10,
+    })
+    .unwrap();
+
+    let uri = format!("http://example.com/{}", "a".repeat(120));
+    let osc = format!("\x1b]8;;{}\x1b\\link\x1b]8;;\x1b\\", uri);
+    for chunk in osc.as_bytes().chunks(30) {
+        vterm.write(chunk).unwrap();
+    }
+
+    assert_eq!(
+        vterm.screen_get_cell(&Pos::new(0, 0)).hyperlink,
+        Some(link(&uri, None))
+    );
+}
+
+#[test]
+fn hyperlink_spans_split_between_links_and_cover_wide_characters() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 10,
+    })
+    .unwrap();
+
+    vterm
+        .write("\x1b]8;;a\x07\u{4e00}b\x1b]8;;c\x07d\x1b[1md\x1b]8;;\x07".as_bytes())
+        .unwrap();
+
+    assert_eq!(
+        vterm.screen_get_hyperlinks(&row(&vterm, 0)),
+        vec![
+            HyperlinkSpan {
+                hyperlink: link("a", None),
+                rect: Rect::new(Pos::new(0, 0), Size::new(3, 1)),
+            },
+            HyperlinkSpan {
+                hyperlink: link("c", None),
+                rect: Rect::new(Pos::new(3, 0), Size::new(2, 1)),
+            },
+        ]
+    );
+}
+
+#[test]
+fn hyperlink_is_not_left_on_erased_cells() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 10,
+    })
+    .unwrap();
+
+    vterm.write(b"\x1b]8;;a\x07link\r\x1b[K").unwrap();
+
+    assert_eq!(vterm.screen_get_hyperlinks(&row(&vterm, 0)), vec![]);
+}
+
+#[test]
+fn hyperlink_survives_scrollback() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 10,
+    })
+    .unwrap();
+    vterm.screen_set_scrollback(Scrollback::with_max_lines(100));
+
+    vterm
+        .write(b"\x1b]8;;a\x07link\x1b]8;;\x07\r\none\r\ntwo")
+        .unwrap();
+    assert_eq!(
+        vterm.scrollback_line(0).unwrap().cells()[0].hyperlink,
+        Some(link("a", None))
+    );
+
+    vterm.set_size(&Size {
+        height: 3,
+        width: 10,
+    });
+    assert_eq!(
+        vterm.screen_get_hyperlinks(&row(&vterm, 0)),
+        vec![HyperlinkSpan {
+            hyperlink: link("a", None),
+            rect: Rect::new(Pos::new(0, 0), Size::new(4, 1)),
+        }]
+    );
+}
+
+#[test]
+fn hyperlink_osc_is_not_passed_to_the_unrecognised_handler() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 10,
+    })
+    .unwrap();
+    let count = Rc::new(RefCell::new(0));
+    let seen = count.clone();
+    vterm.set_unrecognised_handler(Box::new(move |_: &Sequence, _: &mut Vec<u8>| {
+        *seen.borrow_mut() += 1;
+        true
+    }));
+
+    vterm.write(b"\x1b]8;;a\x07link\x1b]8;;\x07").unwrap();
+
+    assert_eq!(*count.borrow(), 0);
+    assert_eq!(vterm.screen_get_hyperlinks(&row(&vterm, 0)).len(), 1);
+}
@@ -0,0 +1,128 @@
+use std::io::prelude::*;
+use vterm_sys::*;
+
+fn read_output(vterm: &mut VTerm) -> Vec<u8> {
+    let mut output = vec![];
+    vterm.read_to_end(&mut output).unwrap();
+    output
+}
+
+#[test]
+fn keyboard_key_encodes_arrow_keys() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.keyboard_key(Key::Up, Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"\x1b[A");
+
+    // DECCKM
+    vterm.write(b"\x1b[?1h").unwrap();
+    vterm.keyboard_key(Key::Up, Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"\x1bOA");
+}
+
+#[test]
+fn keyboard_key_encodes_combined_modifiers() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.keyboard_key(Key::Left, Modifiers::SHIFT | Modifiers::CTRL);
+    assert_eq!(read_output(&mut vterm), b"\x1b[1;6D");
+}
+
+#[test]
+fn keyboard_key_encodes_function_keys() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.keyboard_key(Key::Function(1), Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"\x1b[P");
+
+    vterm.keyboard_key(Key::Function(5), Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"\x1b[15~");
+}
+
+#[test]
+fn keyboard_key_encodes_keypad_keys() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.keyboard_key(Key::Kp1, Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"1");
+
+    // DECKPAM
+    vterm.write(b"\x1b=").unwrap();
+    vterm.keyboard_key(Key::Kp1, Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"\x1bOq");
+}
+
+#[test]
+fn keyboard_char_encodes_plain_and_modified_chars() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.keyboard_char('a', Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"a");
+
+    vterm.keyboard_char('a', Modifiers::CTRL);
+    assert_eq!(read_output(&mut vterm), b"\x01");
+
+    vterm.keyboard_char('a', Modifiers::ALT);
+    assert_eq!(read_output(&mut vterm), b"\x1ba");
+
+    vterm.keyboard_char('é', Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), "é".as_bytes());
+}
+
+#[test]
+fn paste_sends_text_unbracketed_by_default() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    assert!(!vterm.keyboard_is_bracketed_paste());
+
+    vterm.paste("ls -l");
+    assert_eq!(read_output(&mut vterm), b"ls -l");
+}
+
+#[test]
+fn paste_brackets_text_when_enabled() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.write(b"\x1b[?2004h").unwrap();
+    assert!(vterm.keyboard_is_bracketed_paste());
+
+    vterm.paste("ls -l");
+    assert_eq!(read_output(&mut vterm), b"\x1b[200~ls -l\x1b[201~");
+}
+
+#[test]
+fn paste_cannot_break_out_of_bracket() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.write(b"\x1b[?2004h").unwrap();
+
+    vterm.paste("a\x1b[201~rm -rf /\u{9b}201~b");
+    assert_eq!(
+        read_output(&mut vterm),
+        b"\x1b[200~a[201~rm -rf /201~b\x1b[201~"
+    );
+}
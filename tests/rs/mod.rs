@@ -1,3 +1,17 @@
+mod clipboard;
+mod hyperlink;
+mod keyboard;
+mod mouse;
+mod palette;
+mod parser;
+mod renderer;
 mod screen;
 mod screen_callbacks;
+mod screen_handler;
+mod scrollback;
+mod serialize;
+mod snapshot;
 mod state_callbacks;
+mod state_handler;
+mod unrecognised;
+mod vterm;
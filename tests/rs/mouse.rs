@@ -0,0 +1,103 @@
+use std::io::prelude::*;
+use vterm_sys::*;
+
+fn read_output(vterm: &mut VTerm) -> Vec<u8> {
+    let mut output = vec![];
+    vterm.read_to_end(&mut output).unwrap();
+    output
+}
+
+#[test]
+fn mouse_does_nothing_when_tracking_is_off() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 5,
+        width: 5,
+    })
+    .unwrap();
+    vterm.mouse_move(&Pos::new(2, 1), Modifiers::NONE);
+    vterm.mouse_button(MouseButton::Left, true, Modifiers::NONE);
+    vterm.mouse_button(MouseButton::Left, false, Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"");
+}
+
+#[test]
+fn mouse_can_report_x10_encoding() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 5,
+        width: 5,
+    })
+    .unwrap();
+    vterm.write(b"\x1b[?1000h").unwrap();
+
+    vterm.mouse_move(&Pos::new(2, 1), Modifiers::NONE);
+    vterm.mouse_button(MouseButton::Left, true, Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"\x1b[M #\"");
+
+    vterm.mouse_button(MouseButton::Left, false, Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"\x1b[M##\"");
+
+    vterm.mouse_button(MouseButton::Right, true, Modifiers::SHIFT);
+    assert_eq!(read_output(&mut vterm), b"\x1b[M&#\"");
+}
+
+#[test]
+fn mouse_can_report_sgr_encoding() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 5,
+        width: 5,
+    })
+    .unwrap();
+    vterm.write(b"\x1b[?1000h\x1b[?1006h").unwrap();
+
+    vterm.mouse_move(&Pos::new(2, 1), Modifiers::NONE);
+    vterm.mouse_button(MouseButton::Left, true, Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"\x1b[<0;3;2M");
+
+    vterm.mouse_button(MouseButton::Left, false, Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"\x1b[<0;3;2m");
+
+    vterm.mouse_button(MouseButton::Middle, true, Modifiers::CTRL);
+    assert_eq!(read_output(&mut vterm), b"\x1b[<17;3;2M");
+
+    vterm.mouse_button(MouseButton::WheelUp, true, Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"\x1b[<64;3;2M");
+
+    vterm.mouse_button(MouseButton::WheelDown, true, Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"\x1b[<65;3;2M");
+}
+
+#[test]
+fn mouse_can_report_urxvt_encoding() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 5,
+        width: 5,
+    })
+    .unwrap();
+    vterm.write(b"\x1b[?1000h\x1b[?1015h").unwrap();
+
+    vterm.mouse_move(&Pos::new(2, 1), Modifiers::NONE);
+    vterm.mouse_button(MouseButton::Left, true, Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"\x1b[0;3;2M");
+
+    vterm.mouse_button(MouseButton::Left, false, Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"\x1b[3;3;2M");
+}
+
+#[test]
+fn mouse_can_report_drag() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 5,
+        width: 5,
+    })
+    .unwrap();
+    vterm.write(b"\x1b[?1002h\x1b[?1006h").unwrap();
+
+    vterm.mouse_move(&Pos::new(1, 1), Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"");
+
+    vterm.mouse_button(MouseButton::Left, true, Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"\x1b[<0;2;2M");
+
+    vterm.mouse_move(&Pos::new(3, 2), Modifiers::NONE);
+    assert_eq!(read_output(&mut vterm), b"\x1b[<32;4;3M");
+}
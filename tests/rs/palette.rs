@@ -0,0 +1,138 @@
+use std::io::prelude::*;
+use vterm_sys::*;
+
+fn new_vterm() -> VTerm {
+    VTerm::new(&Size {
+        height: 2,
+        width: 10,
+    })
+    .unwrap()
+}
+
+fn read_output(vterm: &mut VTerm) -> Vec<u8> {
+    let mut output = vec![];
+    ::std::io::copy(vterm, &mut output).unwrap();
+    output
+}
+
+fn rgb(red: u8, green: u8, blue: u8) -> ColorRGB {
+    ColorRGB {
+        red: red,
+        green: green,
+        blue: blue,
+    }
+}
+
+#[test]
+fn palette_starts_as_the_default() {
+    let vterm = new_vterm();
+    assert_eq!(vterm.state_get_palette(), Palette::default());
+}
+
+#[test]
+fn palette_color_is_used_by_sgr() {
+    let mut vterm = new_vterm();
+    vterm.state_set_palette_color(1, &rgb(1, 2, 3));
+    vterm.state_set_palette_color(200, &rgb(4, 5, 6));
+
+    vterm.write(b"\x1b[31ma\x1b[38;5;200mb").unwrap();
+
+    assert_eq!(vterm.screen_get_cell(&Pos::new(0, 0)).fg_rgb, rgb(1, 2, 3));
+    assert_eq!(vterm.screen_get_cell(&Pos::new(1, 0)).fg_rgb, rgb(4, 5, 6));
+    assert_eq!(vterm.state_get_palette().colors[200], rgb(4, 5, 6));
+}
+
+#[test]
+fn palette_can_be_set_in_one_go() {
+    let mut vterm = new_vterm();
+    let mut palette = Palette::default();
+    palette.colors[0] = rgb(9, 9, 9);
+    palette.colors[255] = rgb(8, 8, 8);
+    palette.default_fg = rgb(7, 7, 7);
+
+    vterm.state_set_palette(&palette);
+
+    assert_eq!(vterm.state_get_palette(), palette);
+}
+
+#[test]
+fn palette_answers_osc_color_queries() {
+    let mut vterm = new_vterm();
+    vterm.state_set_palette_color(1, &rgb(0x12, 0x34, 0x56));
+    vterm.state_set_default_colors(&rgb(0xff, 0xff, 0xff), &rgb(0, 0, 0x80));
+
+    vterm
+        .write(b"\x1b]4;1;?;300;?\x07\x1b]10;?;?\x1b\\")
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(read_output(&mut vterm)).unwrap(),
+        "\x1b]4;1;rgb:1212/3434/5656\x1b\\\
+         \x1b]10;rgb:ffff/ffff/ffff\x1b\\\
+         \x1b]11;rgb:0000/0000/8080\x1b\\"
+    );
+}
+
+#[test]
+fn palette_is_changed_and_reset_by_osc() {
+    let mut vterm = new_vterm();
+    vterm.state_set_palette_color(2, &rgb(0, 0x80, 0));
+
+    vterm
+        .write(b"\x1b]4;1;#ff0000;2;rgb:00/00/ff\x07\x1b]11;#102030\x07")
+        .unwrap();
+    let palette = vterm.state_get_palette();
+    assert_eq!(palette.colors[1], rgb(0xff, 0, 0));
+    assert_eq!(palette.colors[2], rgb(0, 0, 0xff));
+    assert_eq!(palette.default_bg, rgb(0x10, 0x20, 0x30));
+
+    vterm.write(b"\x1b]104;2\x07\x1b]111\x07").unwrap();
+    let palette = vterm.state_get_palette();
+    assert_eq!(palette.colors[1], rgb(0xff, 0, 0));
+    assert_eq!(palette.colors[2], rgb(0, 0x80, 0));
+    assert_eq!(palette.default_bg, Palette::default().default_bg);
+
+    vterm.write(b"\x1b]104\x07").unwrap();
+    assert_eq!(
+        vterm.state_get_palette().colors[1],
+        Palette::default().colors[1]
+    );
+    assert_eq!(read_output(&mut vterm), vec![]);
+}
+
+#[test]
+fn palette_osc_can_be_split_over_writes() {
+    let mut vterm = new_vterm();
+
+    // Twenty entries, colour n set to rgb(n, n, n), then a query for the last one
+    let mut osc = "\x1b]4".to_string();
+    for n in 0..20 {
+        osc.push_str(&format!(";{};rgb:{:02x}/{:02x}/{:02x}", n, n, n, n));
+    }
+    osc.push_str(";19;?\x1b\\");
+    for chunk in osc.as_bytes().chunks(25) {
+        vterm.write(chunk).unwrap();
+    }
+
+    let palette = vterm.state_get_palette();
+    for n in 0..20 {
+        assert_eq!(palette.colors[n], rgb(n as u8, n as u8, n as u8));
+    }
+    assert_eq!(
+        String::from_utf8(read_output(&mut vterm)).unwrap(),
+        "\x1b]4;19;rgb:1313/1313/1313\x1b\\"
+    );
+}
+
+#[test]
+fn palette_indexes_of_cells_follow_palette_changes() {
+    let mut vterm = new_vterm();
+    vterm.write(b"\x1b[38;2;1;2;3ma").unwrap();
+    assert_eq!(vterm.screen_get_cell(&Pos::new(0, 0)).fg_palette, 0);
+
+    vterm.state_set_palette_color(100, &rgb(1, 2, 3));
+    assert_eq!(vterm.screen_get_cell(&Pos::new(0, 0)).fg_palette, 100);
+
+    vterm.write(b"\x1b]4;50;rgb:01/02/03\x07").unwrap();
+    assert_eq!(vterm.screen_get_cell(&Pos::new(0, 0)).fg_palette, 50);
+}
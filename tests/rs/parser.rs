@@ -0,0 +1,176 @@
+#![allow(unused_variables)]
+
+use std::cell::RefCell;
+use std::io::prelude::*;
+use std::rc::Rc;
+use vterm_sys::*;
+
+fn parse(input: &[u8]) -> Vec<ParserEvent> {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 10,
+    })
+    .unwrap();
+    vterm.parser_receive_events();
+    vterm.write(input).unwrap();
+
+    vterm.parser_event_rx.as_ref().unwrap().try_iter().collect()
+}
+
+fn arg(value: u32) -> CsiArg {
+    CsiArg {
+        value: Some(value),
+        has_more: false,
+    }
+}
+
+#[test]
+fn parser_splits_text_and_controls() {
+    assert_eq!(
+        parse(b"ab\r\ncd"),
+        vec![
+            ParserEvent::Text(b"ab".to_vec()),
+            ParserEvent::Control(b'\r'),
+            ParserEvent::Control(b'\n'),
+            ParserEvent::Text(b"cd".to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn parser_turns_7bit_c1_into_controls() {
+    assert_eq!(
+        parse(b"\x1bD\x1b(B"),
+        vec![
+            ParserEvent::Control(0x84),
+            ParserEvent::Escape(b"(B".to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn parser_receives_csi() {
+    assert_eq!(
+        parse(b"\x1b[?25h\x1b[;3H\x1b[ q"),
+        vec![
+            ParserEvent::Csi {
+                leader: b"?".to_vec(),
+                args: vec![arg(25)],
+                intermed: vec![],
+                command: b'h',
+            },
+            ParserEvent::Csi {
+                leader: vec![],
+                args: vec![
+                    CsiArg {
+                        value: None,
+                        has_more: false,
+                    },
+                    arg(3),
+                ],
+                intermed: vec![],
+                command: b'H',
+            },
+            ParserEvent::Csi {
+                leader: vec![],
+                args: vec![CsiArg {
+                    value: None,
+                    has_more: false,
+                }],
+                intermed: b" ".to_vec(),
+                command: b'q',
+            },
+        ]
+    );
+}
+
+#[test]
+fn parser_flags_csi_sub_parameters() {
+    let events = parse(b"\x1b[38:2:1:2:3;1m");
+    let args = match events[0] {
+        ParserEvent::Csi { ref args, .. } => args.clone(),
+        ref other => panic!("expected csi, got {:?}", other),
+    };
+
+    let has_more: Vec<bool> = args.iter().map(|arg| arg.has_more).collect();
+    let values: Vec<u32> = args.iter().map(|arg| arg.value_or(0)).collect();
+    assert_eq!(has_more, vec![true, true, true, true, false, false]);
+    assert_eq!(values, vec![38, 2, 1, 2, 3, 1]);
+}
+
+#[test]
+fn parser_receives_osc_and_dcs() {
+    assert_eq!(
+        parse(b"\x1b]2;title\x07\x1bP+q544e\x1b\\"),
+        vec![
+            ParserEvent::Osc(b"2;title".to_vec()),
+            ParserEvent::Dcs(b"+q544e".to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn parser_joins_sequences_split_across_writes() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 10,
+    })
+    .unwrap();
+    vterm.parser_receive_events();
+    vterm.write(b"\x1b]0;ti").unwrap();
+    vterm.write(b"tle\x07").unwrap();
+
+    let events: Vec<ParserEvent> = vterm.parser_event_rx.as_ref().unwrap().try_iter().collect();
+    assert_eq!(events, vec![ParserEvent::Osc(b"0;title".to_vec())]);
+}
+
+#[test]
+fn parser_leaves_the_screen_alone_but_still_resizes_it() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.parser_receive_events();
+    vterm.write(b"ab").unwrap();
+    assert_eq!(
+        vterm
+            .screen_get_text_lossy(&Rect::new(Pos::new(0, 0), vterm.get_size()))
+            .trim(),
+        ""
+    );
+
+    let size = Size {
+        height: 3,
+        width: 4,
+    };
+    vterm.set_size(&size);
+    assert_eq!(
+        vterm.parser_event_rx.as_ref().unwrap().try_iter().last(),
+        Some(ParserEvent::Resize(size.clone()))
+    );
+    assert!(vterm.try_screen_get_cell(&Pos::new(3, 2)).is_ok());
+}
+
+struct ControlCounter(Rc<RefCell<usize>>);
+
+impl ParserHandler for ControlCounter {
+    fn control(&mut self, control: u8) {
+        *self.0.borrow_mut() += 1;
+    }
+}
+
+#[test]
+fn parser_handler_receives_callbacks() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    let count = Rc::new(RefCell::new(0));
+    vterm.set_parser_handler(Box::new(ControlCounter(count.clone())));
+
+    vterm.write(b"a\x07b\x07\x1b[m").unwrap();
+
+    assert_eq!(*count.borrow(), 2);
+}
@@ -0,0 +1,275 @@
+use proptest::prelude::*;
+use std::io::prelude::*;
+use vterm_sys::*;
+
+/// Draw the source's screen and pass the output to the host, returning the output
+fn draw(renderer: &mut Renderer, source: &VTerm, host: &mut VTerm) -> Vec<u8> {
+    let mut out: Vec<u8> = vec![];
+    renderer.draw(&source.snapshot(), &mut out).unwrap();
+    host.write(&out).unwrap();
+    out
+}
+
+/// Check the host shows the source's screen at the offset. Blank cells are drawn as spaces, so
+/// cells are compared by how they display. libvterm leaves the attributes of the right half of a
+/// wide character as they were, so only its width is compared. A combining character left alone in
+/// a cell is drawn on a space.
+///
+/// libvterm takes a cell's width from the cell after it, so after half of a wide character is
+/// overwritten the widths don't always match the characters. The host shows each character at its
+/// own width. A right half that nothing covers only has to look blank.
+fn assert_shows(host: &VTerm, source: &VTerm, offset: Pos) {
+    let source = source.snapshot();
+    let host = host.snapshot();
+    let is_wide = |cell: &ScreenCell| cell.chars == vec!['\u{6f22}'];
+
+    for y in 0..source.size.height {
+        for x in 0..source.size.width {
+            let mut want = source.cell(&Pos::new(x, y)).unwrap().clone();
+            let got = host.cell(&Pos::new(offset.x + x, offset.y + y)).unwrap();
+            let at = format!("at {},{}", x, y);
+
+            let covered = x > 0 && is_wide(source.cell(&Pos::new(x - 1, y)).unwrap());
+            if want.width == 0 && !covered {
+                assert_eq!(got.to_string().trim(), "", "{}", at);
+                continue;
+            }
+            if want.width == 2 && !is_wide(&want) {
+                want.width = 1;
+            }
+
+            assert_eq!(got.width, want.width, "{}", at);
+            if want.width == 0 {
+                continue;
+            }
+            if want.chars.first() == Some(&'\u{301}') {
+                assert_eq!(got.to_string(), format!(" {}", want), "{}", at);
+            } else {
+                assert_eq!(got.to_string(), want.to_string(), "{}", at);
+            }
+            assert_eq!(got.attrs, want.attrs, "{}", at);
+            assert_eq!(got.fg, want.fg, "{}", at);
+            assert_eq!(got.bg, want.bg, "{}", at);
+            assert_eq!(got.hyperlink, want.hyperlink, "{}", at);
+        }
+    }
+
+    assert_eq!(host.cursor_visible, source.cursor_visible);
+    if source.cursor_visible {
+        assert_eq!(
+            host.cursor_pos,
+            Pos::new(
+                offset.x + source.cursor_pos.x,
+                offset.y + source.cursor_pos.y
+            )
+        );
+        assert_eq!(host.cursor_shape, source.cursor_shape);
+    }
+}
+
+#[test]
+fn renderer_paints_everything_at_first() {
+    let mut source: VTerm = VTerm::new(&Size::new(6, 2)).unwrap();
+    let mut host: VTerm = VTerm::new(&Size::new(6, 2)).unwrap();
+    source
+        .write(b"\x1b[1;31mred\x1b[m\r\n\x1b[48;5;200mpink")
+        .unwrap();
+
+    let mut renderer = Renderer::new();
+    draw(&mut renderer, &source, &mut host);
+    assert_shows(&host, &source, Pos::new(0, 0));
+}
+
+#[test]
+fn renderer_sends_nothing_when_nothing_changed() {
+    let mut source: VTerm = VTerm::new(&Size::new(6, 2)).unwrap();
+    let mut host: VTerm = VTerm::new(&Size::new(6, 2)).unwrap();
+    source.write(b"hi").unwrap();
+
+    let mut renderer = Renderer::new();
+    draw(&mut renderer, &source, &mut host);
+    assert_eq!(draw(&mut renderer, &source, &mut host), b"");
+}
+
+#[test]
+fn renderer_sends_only_what_changed() {
+    let mut source: VTerm = VTerm::new(&Size::new(10, 2)).unwrap();
+    let mut host: VTerm = VTerm::new(&Size::new(10, 2)).unwrap();
+    source.write(b"abcdefghij\r\nklm").unwrap();
+
+    let mut renderer = Renderer::new();
+    draw(&mut renderer, &source, &mut host);
+
+    source.write(b"n").unwrap();
+    assert_eq!(draw(&mut renderer, &source, &mut host), b"n");
+
+    source.write(b"\x1b[1;2H\x1b[1mB").unwrap();
+    assert_eq!(
+        draw(&mut renderer, &source, &mut host),
+        b"\x1b[1;2H\x1b[1mB".to_vec()
+    );
+    assert_shows(&host, &source, Pos::new(0, 0));
+}
+
+#[test]
+fn renderer_scrolls_the_host() {
+    let mut source: VTerm = VTerm::new(&Size::new(5, 4)).unwrap();
+    let mut host: VTerm = VTerm::new(&Size::new(5, 4)).unwrap();
+    source.write(b"one\r\ntwo\r\nsix\r\nten").unwrap();
+
+    let mut renderer = Renderer::new();
+    draw(&mut renderer, &source, &mut host);
+
+    source.write(b"\r\nnew").unwrap();
+    let out = draw(&mut renderer, &source, &mut host);
+    assert_eq!(
+        out,
+        b"\x1b[1;4r\x1b[1S\x1b[r\x1b[4;1Hnew".to_vec(),
+        "{:?}",
+        String::from_utf8_lossy(&out)
+    );
+    assert_shows(&host, &source, Pos::new(0, 0));
+}
+
+#[test]
+fn renderer_redraws_moved_rows_at_an_offset() {
+    let mut source: VTerm = VTerm::new(&Size::new(5, 4)).unwrap();
+    let mut host: VTerm = VTerm::new(&Size::new(12, 8)).unwrap();
+    source.write(b"one\r\ntwo\r\nsix\r\nten").unwrap();
+
+    let mut renderer = Renderer::with_offset(Pos::new(3, 2));
+    draw(&mut renderer, &source, &mut host);
+    assert_shows(&host, &source, Pos::new(3, 2));
+
+    source.write(b"\r\nnew").unwrap();
+    let out = draw(&mut renderer, &source, &mut host);
+    assert!(!out.contains(&b'r'), "{:?}", String::from_utf8_lossy(&out));
+    assert_shows(&host, &source, Pos::new(3, 2));
+
+    // Nothing was drawn outside the screen
+    assert_eq!(
+        host.screen_get_text(&Rect::new(Pos::new(0, 0), Size::new(12, 2))),
+        Ok("\n".to_string())
+    );
+}
+
+#[test]
+fn renderer_draws_hyperlinks_and_the_cursor() {
+    let mut source: VTerm = VTerm::new(&Size::new(8, 2)).unwrap();
+    let mut host: VTerm = VTerm::new(&Size::new(8, 2)).unwrap();
+    source
+        .write(b"\x1b]8;id=1;http://a\x1b\\link\x1b]8;;\x1b\\ \x1b[4 q")
+        .unwrap();
+
+    let mut renderer = Renderer::new();
+    draw(&mut renderer, &source, &mut host);
+    assert_shows(&host, &source, Pos::new(0, 0));
+
+    source.write(b"\x1b[?25l").unwrap();
+    assert_eq!(draw(&mut renderer, &source, &mut host), b"\x1b[?25l");
+    assert_shows(&host, &source, Pos::new(0, 0));
+}
+
+#[test]
+fn renderer_repaints_after_invalidate() {
+    let mut source: VTerm = VTerm::new(&Size::new(4, 1)).unwrap();
+    let mut host: VTerm = VTerm::new(&Size::new(4, 1)).unwrap();
+    source.write(b"ab").unwrap();
+
+    let mut renderer = Renderer::new();
+    draw(&mut renderer, &source, &mut host);
+    host.write(b"\x1b[Hjunk").unwrap();
+
+    renderer.invalidate();
+    draw(&mut renderer, &source, &mut host);
+    assert_shows(&host, &source, Pos::new(0, 0));
+}
+
+#[test]
+fn renderer_draws_combining_characters() {
+    let mut source: VTerm = VTerm::new(&Size::new(4, 3)).unwrap();
+    let mut host: VTerm = VTerm::new(&Size::new(4, 3)).unwrap();
+    source.set_utf8(true);
+    host.set_utf8(true);
+    // libvterm puts a combining character that follows the last column in a cell of its own on
+    // the next row
+    source
+        .write("xe\u{301}y\r\nabce\u{301}".as_bytes())
+        .unwrap();
+    assert_eq!(
+        source.snapshot().cell(&Pos::new(0, 2)).unwrap().chars,
+        vec!['\u{301}']
+    );
+
+    let mut renderer = Renderer::new();
+    draw(&mut renderer, &source, &mut host);
+    assert_shows(&host, &source, Pos::new(0, 0));
+}
+
+/// Pieces of terminal output that between them write text, move the cursor, scroll, change
+/// attributes and open hyperlinks
+fn output_piece() -> impl Strategy<Value = &'static str> {
+    prop::sample::select(vec![
+        "a",
+        "bc",
+        "def ",
+        "\u{6f22}",
+        "\r\n",
+        "\x08",
+        "\x1b[1m",
+        "\x1b[3;4m",
+        "\x1b[21m",
+        "\x1b[5;7;9m",
+        "\x1b[12m",
+        "\x1b[31m",
+        "\x1b[95m",
+        "\x1b[48;5;200m",
+        "\x1b[38;2;1;2;3m",
+        "\x1b[m",
+        "\x1b]8;;http://a\x1b\\",
+        "\x1b]8;id=x;http://b\x1b\\",
+        "\x1b]8;;\x1b\\",
+        "\x1b[H",
+        "\x1b[2;3H",
+        "\x1b[K",
+        "\x1b[2J",
+        "\x1b[S",
+        "\x1b[2T",
+        "\x1b[2;3r",
+        "\x1b[r",
+        "\x1b[L",
+        "\x1b[2P",
+        "\x1b[?25l",
+        "\x1b[?25h",
+        "\x1b[6 q",
+    ])
+}
+
+proptest! {
+    #[test]
+    fn renderer_keeps_the_host_matching(
+        width in 2usize..8,
+        height in 1usize..6,
+        offset in prop::option::of((0usize..3, 0usize..3)),
+        steps in prop::collection::vec(prop::collection::vec(output_piece(), 0..12), 1..6),
+    ) {
+        let mut source: VTerm = VTerm::new(&Size::new(width, height)).unwrap();
+        source.set_utf8(true);
+        let (mut renderer, offset, host_size) = match offset {
+            Some((x, y)) => (
+                Renderer::with_offset(Pos::new(x, y)),
+                Pos::new(x, y),
+                Size::new(width + x + 2, height + y + 2),
+            ),
+            None => (Renderer::new(), Pos::new(0, 0), Size::new(width, height)),
+        };
+        let mut host: VTerm = VTerm::new(&host_size).unwrap();
+        host.set_utf8(true);
+
+        for step in steps {
+            source.write(step.concat().as_bytes()).unwrap();
+            draw(&mut renderer, &source, &mut host);
+            assert_shows(&host, &source, offset);
+        }
+    }
+}
@@ -15,7 +15,71 @@ fn screen_get_cell_returns_cell() {
     vterm.flush().unwrap();
 
     let cell = vterm.screen_get_cell(&Pos::new(0, 0));
-    assert_eq!(cell.chars[0], b'a');
+    assert_eq!(cell.chars[0], 'a');
+}
+
+#[test]
+fn screen_cells_keep_combining_characters_in_order() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 1,
+        width: 4,
+    })
+    .unwrap();
+    vterm.set_utf8(true);
+    vterm.write("e\u{301}\u{323}x".as_bytes()).unwrap();
+
+    let cell = vterm.screen_get_cell(&Pos::new(0, 0));
+    assert_eq!(cell.chars, vec!['e', '\u{301}', '\u{323}']);
+    assert_eq!(cell.text(), "e\u{301}\u{323}");
+    assert_eq!(cell.width, 1);
+    assert_eq!(vterm.screen_get_cell(&Pos::new(1, 0)).text(), "x");
+}
+
+#[test]
+fn screen_cells_hold_wide_characters() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 1,
+        width: 6,
+    })
+    .unwrap();
+    vterm.set_utf8(true);
+    vterm.write("漢\u{1f600}a".as_bytes()).unwrap();
+
+    let cells = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(6, 1)));
+    assert_eq!(
+        cells
+            .iter()
+            .map(|c| (c.text(), c.width))
+            .collect::<Vec<(String, u8)>>(),
+        vec![
+            ("漢".to_string(), 2),
+            ("".to_string(), 0),
+            // The vendored width table predates wide emoji, so they take one cell
+            ("\u{1f600}".to_string(), 1),
+            ("a".to_string(), 1),
+            ("".to_string(), 1),
+            ("".to_string(), 1),
+        ]
+    );
+
+    // Displaying each cell in turn gives back the line
+    let line: String = cells.iter().map(|c| c.to_string()).collect();
+    assert_eq!(line, "漢\u{1f600}a  ");
+}
+
+#[test]
+fn screen_survives_wide_characters_on_a_one_column_screen() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 1,
+    })
+    .unwrap();
+    vterm.set_utf8(true);
+    vterm.write("漢a".as_bytes()).unwrap();
+
+    let cell = vterm.screen_get_cell(&Pos::new(0, 0));
+    assert_eq!(cell.text(), "漢");
+    assert_eq!(cell.width, 1);
 }
 
 #[test]
@@ -29,6 +93,29 @@ fn screen_get_cell_panics_if_out_of_bounds() {
     vterm.screen_get_cell(&Pos::new(1, 0));
 }
 
+#[test]
+fn try_screen_get_cell_returns_error_if_out_of_bounds() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    assert!(vterm.try_screen_get_cell(&Pos::new(1, 1)).is_ok());
+
+    // The screen can shrink out from under a position
+    vterm.set_size(&Size {
+        height: 1,
+        width: 1,
+    });
+    assert_eq!(
+        vterm.try_screen_get_cell(&Pos::new(1, 1)),
+        Err(Error::OutOfBounds {
+            size: Size::new(1, 1),
+            pos: Pos::new(1, 1),
+        })
+    );
+}
+
 #[test]
 fn screen_get_text_lossy_returns_string() {
     let mut vterm: VTerm = VTerm::new(&Size {
@@ -109,16 +196,19 @@ fn screen_get_text_returns_string_from_part_of_screen() {
 }
 
 #[test]
-#[should_panic]
-fn screen_get_text_panics_if_rect_is_out_of_bounds() {
+fn screen_get_text_returns_error_if_rect_is_out_of_bounds() {
     let vterm: VTerm = VTerm::new(&Size {
         height: 1,
         width: 1,
     })
     .unwrap();
-    vterm
-        .screen_get_text(&Rect::new(Pos::new(1, 0), Size::new(1, 2)))
-        .unwrap();
+    assert_eq!(
+        vterm.screen_get_text(&Rect::new(Pos::new(1, 0), Size::new(1, 2))),
+        Err(Error::OutOfBounds {
+            size: Size::new(1, 1),
+            pos: Pos::new(1, 1),
+        })
+    );
 }
 
 #[test]
@@ -133,8 +223,8 @@ fn screen_get_cells_in_rect_returns_cells() {
 
     let cells = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(2, 2)));
     assert_eq!(
-        cells.iter().map(|c| c.chars[0]).collect::<Vec<u8>>(),
-        b"abcd"
+        cells.iter().map(|c| c.chars[0]).collect::<Vec<char>>(),
+        vec!['a', 'b', 'c', 'd']
     );
 }
 
@@ -148,3 +238,58 @@ fn screen_get_cells_in_rect_panics_if_out_of_bounds() {
     .unwrap();
     vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(1, 0), Size::new(2, 2)));
 }
+
+#[test]
+fn try_screen_get_cells_in_rect_returns_error_if_out_of_bounds() {
+    let vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    assert_eq!(
+        vterm.try_screen_get_cells_in_rect(&Rect::new(Pos::new(1, 1), Size::new(2, 1))),
+        Err(Error::OutOfBounds {
+            size: Size::new(2, 2),
+            pos: Pos::new(2, 1),
+        })
+    );
+}
+
+#[test]
+fn screen_cells_know_where_their_colors_came_from() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 5,
+    })
+    .unwrap();
+
+    vterm
+        .write(b"a\x1b[33;100mb\x1b[38;2;1;2;3;49mc\x1b[7m\x1b[38;5;200md")
+        .unwrap();
+    let cells = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(5, 1)));
+
+    assert_eq!(
+        (&cells[0].fg, &cells[0].bg),
+        (&Color::Default, &Color::Default)
+    );
+    assert_eq!(
+        (&cells[1].fg, &cells[1].bg),
+        (&Color::Indexed(3), &Color::Indexed(8))
+    );
+    assert_eq!(
+        cells[2].fg,
+        Color::Rgb(ColorRGB {
+            red: 1,
+            green: 2,
+            blue: 3,
+        })
+    );
+    assert_eq!(cells[2].bg, Color::Default);
+    assert_eq!(cells[3].fg, Color::Indexed(200));
+    assert_eq!(cells[3].fg_palette, 200);
+
+    // Erased cells take the pen's background
+    vterm.write(b"\x1b[m\x1b[44m\x1b[2J").unwrap();
+    let cell = vterm.screen_get_cell(&Pos::new(4, 1));
+    assert_eq!((&cell.fg, &cell.bg), (&Color::Default, &Color::Indexed(4)));
+}
@@ -144,6 +144,226 @@ fn screen_can_generate_cursor_blink_events() {
     assert_eq!(event.is_on, false);
 }
 
+#[test]
+fn screen_can_generate_alt_screen_on_events() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.screen_enable_altscreen(true);
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+
+    let terminfo = TermInfo::from_name("xterm").unwrap();
+    vterm
+        .write(&CapBuilder::new(&terminfo).cap("smcup").build().unwrap())
+        .unwrap();
+    vterm.flush().unwrap();
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    let event = try_recv_alt_screen_event(&rx);
+
+    assert!(event.is_some());
+    let event = event.unwrap();
+    assert_eq!(event.is_on, true);
+
+    vterm
+        .write(&CapBuilder::new(&terminfo).cap("rmcup").build().unwrap())
+        .unwrap();
+    vterm.flush().unwrap();
+
+    let event = try_recv_alt_screen_event(&rx);
+
+    assert!(event.is_some());
+    let event = event.unwrap();
+    assert_eq!(event.is_on, false);
+}
+
+#[test]
+fn screen_can_generate_cursor_shape_events() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+
+    // DECSCUSR
+    vterm.write(b"\x1b[4 q").unwrap();
+    vterm.flush().unwrap();
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    let event = try_recv_cursor_shape_event(&rx);
+
+    assert!(event.is_some());
+    let event = event.unwrap();
+    assert_eq!(event.shape, CursorShape::Underline);
+
+    vterm.write(b"\x1b[6 q").unwrap();
+    vterm.flush().unwrap();
+
+    let event = try_recv_cursor_shape_event(&rx);
+
+    assert!(event.is_some());
+    let event = event.unwrap();
+    assert_eq!(event.shape, CursorShape::BarLeft);
+
+    vterm.write(b"\x1b[2 q").unwrap();
+    vterm.flush().unwrap();
+
+    let event = try_recv_cursor_shape_event(&rx);
+
+    assert!(event.is_some());
+    let event = event.unwrap();
+    assert_eq!(event.shape, CursorShape::Block);
+}
+
+#[test]
+fn screen_can_generate_cursor_visible_events() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+
+    let terminfo = TermInfo::from_name("xterm").unwrap();
+    vterm
+        .write(&CapBuilder::new(&terminfo).cap("civis").build().unwrap())
+        .unwrap();
+    vterm.flush().unwrap();
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    let event = try_recv_cursor_visible_event(&rx);
+
+    assert!(event.is_some());
+    let event = event.unwrap();
+    assert_eq!(event.is_on, false);
+
+    vterm
+        .write(&CapBuilder::new(&terminfo).cap("cnorm").build().unwrap())
+        .unwrap();
+    vterm.flush().unwrap();
+
+    let event = try_recv_cursor_visible_event(&rx);
+
+    assert!(event.is_some());
+    let event = event.unwrap();
+    assert_eq!(event.is_on, true);
+}
+
+#[test]
+fn screen_can_generate_title_events() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+
+    // OSC 2
+    vterm.write(b"\x1b]2;foo\x1b\\").unwrap();
+    vterm.flush().unwrap();
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    let event = try_recv_title_event(&rx);
+
+    assert!(event.is_some());
+    let event = event.unwrap();
+    assert_eq!(event.title, "foo");
+
+    // OSC 0 sets both the title and icon name
+    vterm.write(b"\x1b]0;bar\x07").unwrap();
+    vterm.flush().unwrap();
+
+    let event = try_recv_title_event(&rx);
+
+    assert!(event.is_some());
+    let event = event.unwrap();
+    assert_eq!(event.title, "bar");
+}
+
+#[test]
+fn screen_can_generate_icon_name_events() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+
+    // OSC 1
+    vterm.write(b"\x1b]1;foo\x1b\\").unwrap();
+    vterm.flush().unwrap();
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    let event = try_recv_icon_name_event(&rx);
+
+    assert!(event.is_some());
+    let event = event.unwrap();
+    assert_eq!(event.name, "foo");
+}
+
+#[test]
+fn screen_can_generate_reverse_events() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+
+    // DECSCNM
+    vterm.write(b"\x1b[?5h").unwrap();
+    vterm.flush().unwrap();
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    let event = try_recv_reverse_event(&rx);
+
+    assert!(event.is_some());
+    let event = event.unwrap();
+    assert_eq!(event.is_on, true);
+
+    vterm.write(b"\x1b[?5l").unwrap();
+    vterm.flush().unwrap();
+
+    let event = try_recv_reverse_event(&rx);
+
+    assert!(event.is_some());
+    let event = event.unwrap();
+    assert_eq!(event.is_on, false);
+}
+
+#[test]
+fn screen_can_generate_mouse_events() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+    let rx = vterm.screen_event_rx.take().unwrap();
+
+    // DECSET for mouse support
+    let cases: Vec<(&[u8], MouseMode)> = vec![
+        (b"\x1b[?1000h", MouseMode::Click),
+        (b"\x1b[?1002h", MouseMode::Drag),
+        (b"\x1b[?1003h", MouseMode::Move),
+        (b"\x1b[?1003l", MouseMode::None),
+    ];
+
+    for (input, mode) in cases {
+        vterm.write(input).unwrap();
+        vterm.flush().unwrap();
+
+        let event = try_recv_mouse_event(&rx);
+
+        assert!(event.is_some());
+        let event = event.unwrap();
+        assert_eq!(event.mode, mode);
+    }
+}
+
 // Builds a function that returns a Some of the first event of the given type found on the channel
 // or None.
 macro_rules! dry {
@@ -181,3 +401,21 @@ dry!(
     CursorBlinkEvent,
     ScreenEvent::CursorBlink
 );
+dry!(
+    try_recv_cursor_shape_event,
+    CursorShapeEvent,
+    ScreenEvent::CursorShape
+);
+dry!(
+    try_recv_cursor_visible_event,
+    CursorVisibleEvent,
+    ScreenEvent::CursorVisible
+);
+dry!(try_recv_title_event, TitleEvent, ScreenEvent::Title);
+dry!(
+    try_recv_icon_name_event,
+    IconNameEvent,
+    ScreenEvent::IconName
+);
+dry!(try_recv_reverse_event, ReverseEvent, ScreenEvent::Reverse);
+dry!(try_recv_mouse_event, MouseEvent, ScreenEvent::Mouse);
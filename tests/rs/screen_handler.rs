@@ -0,0 +1,118 @@
+#![allow(unused_variables)]
+
+use std::cell::RefCell;
+use std::io::prelude::*;
+use std::rc::Rc;
+use vterm_sys::*;
+
+#[derive(Default)]
+struct Recorder {
+    bells: usize,
+    pushed_lines: Vec<char>,
+}
+
+struct RecordingHandler(Rc<RefCell<Recorder>>);
+
+impl ScreenHandler for RecordingHandler {
+    fn bell(&mut self) {
+        self.0.borrow_mut().bells += 1;
+    }
+
+    fn sb_pushline(&mut self, line: &ScreenLine) {
+        let first = line.get(0).unwrap();
+        self.0.borrow_mut().pushed_lines.push(first.chars[0]);
+    }
+}
+
+#[test]
+fn screen_handler_receives_callbacks() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    let recorder = Rc::new(RefCell::new(Recorder::default()));
+    vterm.set_screen_handler(Box::new(RecordingHandler(recorder.clone())));
+
+    vterm.write(b"\x07a\r\nb\r\nc").unwrap();
+
+    let recorder = recorder.borrow();
+    assert_eq!(recorder.bells, 1);
+    assert_eq!(recorder.pushed_lines, vec!['a']);
+}
+
+struct IgnoreEverything;
+
+impl ScreenHandler for IgnoreEverything {}
+
+#[test]
+fn screen_handler_replaces_channel() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+    let rx = vterm.screen_event_rx.take().unwrap();
+
+    vterm.set_screen_handler(Box::new(IgnoreEverything));
+    vterm.write(b"\x07").unwrap();
+
+    assert!(rx.try_recv().is_err());
+}
+
+struct Scrollback(Vec<Vec<ScreenCell>>);
+
+impl ScreenHandler for Scrollback {
+    fn sb_pushline(&mut self, line: &ScreenLine) {
+        self.0.push(line.to_vec());
+    }
+
+    fn sb_popline(&mut self, cols: usize) -> Option<Vec<ScreenCell>> {
+        self.0.pop()
+    }
+}
+
+#[test]
+fn screen_handler_can_restore_scrollback_on_resize() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 3,
+    })
+    .unwrap();
+    vterm.set_screen_handler(Box::new(Scrollback(vec![])));
+
+    vterm.write(b"one\r\ntwo\r\nsix").unwrap();
+    vterm.set_size(&Size {
+        height: 3,
+        width: 3,
+    });
+
+    assert_eq!(
+        vterm.screen_get_text(&Rect::new(Pos::new(0, 0), Size::new(3, 3))),
+        Ok("one\ntwo\nsix".to_string())
+    );
+}
+
+#[test]
+fn screen_handler_pads_short_scrollback_lines() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 1,
+        width: 3,
+    })
+    .unwrap();
+    let mut cell = ScreenCell::default();
+    cell.chars = vec!['x'];
+    vterm.set_screen_handler(Box::new(Scrollback(vec![vec![cell]])));
+
+    vterm.write(b"abc").unwrap();
+    vterm.set_size(&Size {
+        height: 2,
+        width: 3,
+    });
+
+    assert_eq!(
+        vterm.screen_get_text(&Rect::new(Pos::new(0, 0), Size::new(3, 2))),
+        Ok("x\nabc".to_string())
+    );
+}
@@ -0,0 +1,89 @@
+use std::io::prelude::*;
+use vterm_sys::*;
+
+#[test]
+fn scrollback_restores_lines_when_screen_grows() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 5,
+    })
+    .unwrap();
+    vterm.screen_set_scrollback(Scrollback::with_max_lines(100));
+
+    vterm.write(b"one\r\ntwo\r\nsix\r\nten").unwrap();
+    assert_eq!(vterm.scrollback_len(), 2);
+
+    vterm.set_size(&Size {
+        height: 3,
+        width: 5,
+    });
+    assert_eq!(vterm.scrollback_len(), 1);
+    assert_eq!(vterm.scrollback_line(0).unwrap().text(), "one");
+    assert_eq!(
+        vterm.screen_get_text(&Rect::new(Pos::new(0, 0), Size::new(5, 3))),
+        Ok("two\nsix\nten".to_string())
+    );
+}
+
+#[test]
+fn scrollback_works_alongside_screen_events() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 5,
+    })
+    .unwrap();
+    let mut config = ScreenCallbacksConfig::none();
+    config.bell = true;
+    vterm.screen_receive_events(&config);
+    vterm.screen_set_scrollback(Scrollback::with_max_lines(100));
+
+    vterm.write(b"one\r\ntwo\r\nsix\x07").unwrap();
+    assert_eq!(vterm.scrollback_len(), 1);
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    let events: Vec<ScreenEvent> = rx.try_iter().collect();
+    assert_eq!(events.len(), 1);
+    match events[0] {
+        ScreenEvent::Bell => {}
+        _ => panic!("expected only a bell event"),
+    }
+}
+
+#[test]
+fn scrollback_can_be_taken() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 5,
+    })
+    .unwrap();
+    vterm.screen_set_scrollback(Scrollback::with_max_lines(100));
+    vterm.write(b"one\r\ntwo\r\nsix").unwrap();
+
+    let scrollback = vterm.screen_take_scrollback().unwrap();
+    assert_eq!(scrollback.len(), 1);
+    assert_eq!(vterm.scrollback_len(), 0);
+
+    vterm.write(b"\r\nten").unwrap();
+    assert_eq!(vterm.scrollback_len(), 0);
+}
+
+#[test]
+fn scrollback_keeps_wide_and_combining_characters() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    })
+    .unwrap();
+    vterm.set_utf8(true);
+    vterm.screen_set_scrollback(Scrollback::with_max_lines(100));
+    vterm.write("漢e\u{301}\r\n\r\n".as_bytes()).unwrap();
+
+    let line = vterm.scrollback_line(0).unwrap();
+    assert_eq!(line.text(), "漢e\u{301}");
+    let cells = line.cells();
+    assert_eq!(cells[0].chars, vec!['漢']);
+    assert_eq!(cells[0].width, 2);
+    assert_eq!(cells[1].chars, vec![]);
+    assert_eq!(cells[1].width, 0);
+    assert_eq!(cells[2].chars, vec!['e', '\u{301}']);
+}
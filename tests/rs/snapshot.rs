@@ -0,0 +1,417 @@
+use proptest::prelude::*;
+use std::io::prelude::*;
+use std::thread;
+use vterm_sys::*;
+
+#[test]
+fn snapshot_has_every_cell() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 3,
+    })
+    .unwrap();
+    vterm.write(b"ab\r\n\x1b[1mc").unwrap();
+
+    let snapshot = vterm.snapshot();
+    assert_eq!(snapshot.size, Size::new(3, 2));
+    assert_eq!(snapshot.cells.len(), 6);
+    assert_eq!(
+        snapshot.cells,
+        vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(3, 2)))
+    );
+    assert_eq!(
+        snapshot
+            .rows()
+            .map(|row| row.iter().map(|c| c.to_string()).collect::<String>())
+            .collect::<Vec<String>>(),
+        vec!["ab ".to_string(), "c  ".to_string()]
+    );
+    assert!(snapshot.cell(&Pos::new(0, 1)).unwrap().attrs.bold);
+    assert_eq!(snapshot.cell(&Pos::new(3, 0)), None);
+}
+
+#[test]
+fn snapshot_has_the_cursor() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 3,
+        width: 5,
+    })
+    .unwrap();
+
+    let snapshot = vterm.snapshot();
+    assert_eq!(snapshot.cursor_pos, Pos::new(0, 0));
+    assert!(snapshot.cursor_visible);
+    assert_eq!(snapshot.cursor_shape, CursorShape::Block);
+
+    // Move to row 2 column 4, hide the cursor and ask for an underline
+    vterm.write(b"\x1b[2;4H\x1b[?25l\x1b[4 q").unwrap();
+    let snapshot = vterm.snapshot();
+    assert_eq!(snapshot.cursor_pos, Pos::new(3, 1));
+    assert!(!snapshot.cursor_visible);
+    assert_eq!(snapshot.cursor_shape, CursorShape::Underline);
+}
+
+#[test]
+fn snapshot_has_the_title_and_alt_screen() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 5,
+    })
+    .unwrap();
+    vterm.screen_enable_altscreen(true);
+    assert_eq!(vterm.snapshot().title, "");
+
+    vterm.write(b"\x1b]2;hello\x07\x1b[?1049h").unwrap();
+    let snapshot = vterm.snapshot();
+    assert_eq!(snapshot.title, "hello");
+    assert!(snapshot.alt_screen);
+
+    vterm.write(b"\x1b]0;bye\x07\x1b[?1049l").unwrap();
+    let snapshot = vterm.snapshot();
+    assert_eq!(snapshot.title, "bye");
+    assert!(!snapshot.alt_screen);
+}
+
+#[test]
+fn snapshot_title_follows_the_screen_handler() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 5,
+    })
+    .unwrap();
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+    vterm.write(b"\x1b]2;hello\x07").unwrap();
+
+    assert_eq!(vterm.snapshot().title, "hello");
+    let rx = vterm.screen_event_rx.take().unwrap();
+    assert!(rx.try_iter().any(|event| match event {
+        ScreenEvent::Title(e) => e.title == "hello",
+        _ => false,
+    }));
+}
+
+struct AcceptEverything;
+
+impl StateHandler for AcceptEverything {}
+
+#[test]
+fn snapshot_title_can_be_split_over_writes() {
+    let title = "a long title ".repeat(10);
+    let osc = format!("\x1b]2;{}\x07", title);
+
+    // With the screen layer getting the state's callbacks, then with a state handler
+    for &with_state_handler in &[false, true] {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 5,
+        })
+        .unwrap();
+        if with_state_handler {
+            vterm.set_state_handler(Box::new(AcceptEverything));
+        }
+
+        for chunk in osc.as_bytes().chunks(20) {
+            vterm.write(chunk).unwrap();
+        }
+
+        assert_eq!(vterm.snapshot().title, title);
+        let mut buf = vec![];
+        vterm.serialize_state(&mut buf).unwrap();
+        let expected = format!("\x1b]2;{}\x1b\\", title);
+        assert!(String::from_utf8_lossy(&buf).contains(&expected));
+    }
+}
+
+/// Turns down every title that isn't "keep"
+struct TitleFilter;
+
+impl StateHandler for TitleFilter {
+    fn set_term_prop(&mut self, prop: TermProp) -> bool {
+        match prop {
+            TermProp::Title(title) => title == "keep",
+            _ => true,
+        }
+    }
+}
+
+#[test]
+fn snapshot_title_follows_the_state_handler() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 5,
+    })
+    .unwrap();
+    vterm.set_state_handler(Box::new(TitleFilter));
+
+    vterm.write(b"\x1b]2;keep\x07").unwrap();
+    assert_eq!(vterm.snapshot().title, "keep");
+    vterm.write(b"\x1b]2;drop\x07").unwrap();
+    assert_eq!(vterm.snapshot().title, "keep");
+
+    let mut buf = vec![];
+    vterm.serialize_state(&mut buf).unwrap();
+    assert!(String::from_utf8_lossy(&buf).contains("\x1b]2;keep\x1b\\"));
+
+    // Kept even when the handler didn't ask for set_term_prop
+    vterm.state_receive_events(&StateCallbacksConfig::none());
+    vterm.write(b"\x1b]2;bye\x07").unwrap();
+    assert_eq!(vterm.snapshot().title, "bye");
+}
+
+#[test]
+fn snapshot_can_be_sent_to_another_thread() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 1,
+        width: 5,
+    })
+    .unwrap();
+    vterm.write(b"hi").unwrap();
+
+    let snapshot = vterm.snapshot();
+    let copy = snapshot.clone();
+    let text = thread::spawn(move || copy.cells.iter().map(|c| c.to_string()).collect::<String>())
+        .join()
+        .unwrap();
+
+    assert_eq!(text, "hi   ");
+    assert_eq!(snapshot, vterm.snapshot());
+}
+
+/// A VTerm with utf8 on and the given lines written, one per row
+fn vterm_with_lines(size: Size, lines: &[&str]) -> VTerm {
+    let mut vterm: VTerm = VTerm::new(&size).unwrap();
+    vterm.set_utf8(true);
+    vterm.write(lines.join("\r\n").as_bytes()).unwrap();
+    vterm
+}
+
+#[test]
+fn snapshot_diff_of_the_same_snapshot_is_empty() {
+    let vterm = vterm_with_lines(Size::new(4, 2), &["ab", "cd"]);
+    assert_eq!(vterm.snapshot().diff(&vterm.snapshot()), vec![]);
+}
+
+#[test]
+fn snapshot_diff_sends_small_text_changes_as_runs() {
+    let mut vterm = vterm_with_lines(Size::new(8, 2), &["abcdefgh", "ijkl"]);
+    let old = vterm.snapshot();
+    vterm.write(b"\x1b[1;3Hxy\x1b[2;8Hz").unwrap();
+    let new = vterm.snapshot();
+
+    let changes = old.diff(&new);
+    assert_eq!(changes.len(), 3);
+    assert_eq!(
+        changes[0],
+        Change::Text {
+            pos: Pos::new(2, 0),
+            cells: vec![
+                new.cell(&Pos::new(2, 0)).unwrap().clone(),
+                new.cell(&Pos::new(3, 0)).unwrap().clone(),
+            ],
+        }
+    );
+    match changes[1] {
+        Change::Text { pos, ref cells } => {
+            assert_eq!(pos, Pos::new(7, 1));
+            assert_eq!(cells.len(), 1);
+        }
+        ref change => panic!("expected a text change, got {:?}", change),
+    }
+    match changes[2] {
+        Change::Cursor { pos, .. } => assert_eq!(pos, Pos::new(7, 1)),
+        ref change => panic!("expected a cursor change, got {:?}", change),
+    }
+}
+
+#[test]
+fn snapshot_diff_sends_mostly_changed_rows_whole() {
+    let mut vterm = vterm_with_lines(Size::new(4, 1), &["abcd"]);
+    let old = vterm.snapshot();
+    vterm.write(b"\rwxy").unwrap();
+
+    let changes = old.diff(&vterm.snapshot());
+    match changes[0] {
+        Change::Row { y, ref cells } => {
+            assert_eq!(y, 0);
+            assert_eq!(
+                cells.iter().map(|c| c.to_string()).collect::<String>(),
+                "wxyd"
+            );
+        }
+        ref change => panic!("expected a row change, got {:?}", change),
+    }
+}
+
+#[test]
+fn snapshot_diff_tells_attribute_changes_from_text_changes() {
+    let mut vterm = vterm_with_lines(Size::new(8, 1), &["abcdefgh"]);
+    let old = vterm.snapshot();
+    // Rewrite the same text in bold, then change one letter
+    vterm.write(b"\r\x1b[1mab\x1b[mX").unwrap();
+
+    let changes = old.diff(&vterm.snapshot());
+    match changes[0] {
+        Change::Attrs { pos, ref cells } => {
+            assert_eq!(pos, Pos::new(0, 0));
+            assert_eq!(cells.len(), 2);
+            assert!(cells.iter().all(|c| c.attrs.bold));
+        }
+        ref change => panic!("expected an attrs change, got {:?}", change),
+    }
+    match changes[1] {
+        Change::Text { pos, ref cells } => {
+            assert_eq!(pos, Pos::new(2, 0));
+            assert_eq!(cells[0].text(), "X");
+        }
+        ref change => panic!("expected a text change, got {:?}", change),
+    }
+}
+
+#[test]
+fn snapshot_diff_finds_scrolls() {
+    let mut vterm = vterm_with_lines(Size::new(3, 4), &["one", "two", "six", "ten"]);
+    let old = vterm.snapshot();
+    vterm.write(b"\r\nnew").unwrap();
+    let new = vterm.snapshot();
+
+    let changes = old.diff(&new);
+    assert_eq!(
+        changes[0],
+        Change::MoveRect {
+            dest: Rect::new(Pos::new(0, 0), Size::new(3, 3)),
+            src: Rect::new(Pos::new(0, 1), Size::new(3, 3)),
+        }
+    );
+    match changes[1] {
+        Change::Row { y, .. } => assert_eq!(y, 3),
+        ref change => panic!("expected a row change, got {:?}", change),
+    }
+
+    let mut applied = old.clone();
+    applied.apply(&changes);
+    assert_eq!(applied, new);
+}
+
+#[test]
+fn snapshot_diff_finds_scrolls_inside_a_region() {
+    let mut vterm = vterm_with_lines(Size::new(3, 5), &["top", "one", "two", "six", "end"]);
+    let old = vterm.snapshot();
+    // Scroll rows 2 to 4 down by one
+    vterm.write(b"\x1b[2;4r\x1b[T\x1b[r").unwrap();
+    let new = vterm.snapshot();
+
+    let changes = old.diff(&new);
+    assert_eq!(
+        changes[0],
+        Change::MoveRect {
+            dest: Rect::new(Pos::new(0, 2), Size::new(3, 2)),
+            src: Rect::new(Pos::new(0, 1), Size::new(3, 2)),
+        }
+    );
+
+    let mut applied = old.clone();
+    applied.apply(&changes);
+    assert_eq!(applied, new);
+}
+
+#[test]
+fn snapshot_diff_resends_everything_after_a_resize() {
+    let mut vterm = vterm_with_lines(Size::new(3, 2), &["ab", "cd"]);
+    let old = vterm.snapshot();
+    vterm.set_size(&Size::new(4, 3));
+    let new = vterm.snapshot();
+
+    let changes = old.diff(&new);
+    assert_eq!(changes[0], Change::Resize(Size::new(4, 3)));
+    assert_eq!(
+        changes
+            .iter()
+            .filter(|change| matches!(change, Change::Row { .. }))
+            .count(),
+        3
+    );
+
+    let mut applied = old.clone();
+    applied.apply(&changes);
+    assert_eq!(applied, new);
+}
+
+/// Pieces of terminal output that between them write text, move the cursor, scroll, change
+/// attributes and set the title
+fn output_piece() -> impl Strategy<Value = &'static str> {
+    prop::sample::select(vec![
+        "a",
+        "bc",
+        "def ",
+        "\u{6f22}",
+        "e\u{301}",
+        "\r\n",
+        "\x08",
+        "\x1b[1m",
+        "\x1b[3;4m",
+        "\x1b[31m",
+        "\x1b[48;5;200m",
+        "\x1b[38;2;1;2;3m",
+        "\x1b[m",
+        "\x1b[H",
+        "\x1b[2;3H",
+        "\x1b[K",
+        "\x1b[2J",
+        "\x1b[S",
+        "\x1b[2T",
+        "\x1b[2;3r",
+        "\x1b[r",
+        "\x1b[L",
+        "\x1b[2P",
+        "\x1b[?25l",
+        "\x1b[?25h",
+        "\x1b[6 q",
+        "\x1b]2;title\x07",
+        "\x1b[?1049h",
+        "\x1b[?1049l",
+    ])
+}
+
+proptest! {
+    #[test]
+    fn snapshot_diff_applied_to_the_old_snapshot_gives_the_new_one(
+        width in 1usize..8,
+        height in 1usize..6,
+        before in prop::collection::vec(output_piece(), 0..30),
+        after in prop::collection::vec(output_piece(), 0..30),
+        resize in prop::option::of((1usize..8, 1usize..6)),
+    ) {
+        let mut vterm: VTerm = VTerm::new(&Size::new(width, height)).unwrap();
+        vterm.set_utf8(true);
+        vterm.screen_enable_altscreen(true);
+        vterm.write(before.concat().as_bytes()).unwrap();
+        let old = vterm.snapshot();
+
+        vterm.write(after.concat().as_bytes()).unwrap();
+        if let Some((width, height)) = resize {
+            vterm.set_size(&Size::new(width, height));
+        }
+        let new = vterm.snapshot();
+
+        let mut applied = old.clone();
+        applied.apply(&old.diff(&new));
+        prop_assert_eq!(applied, new);
+    }
+
+    #[test]
+    fn snapshot_diff_undoes_itself(
+        before in prop::collection::vec(output_piece(), 0..30),
+        after in prop::collection::vec(output_piece(), 0..30),
+    ) {
+        let mut vterm: VTerm = VTerm::new(&Size::new(6, 4)).unwrap();
+        vterm.set_utf8(true);
+        vterm.write(before.concat().as_bytes()).unwrap();
+        let old = vterm.snapshot();
+        vterm.write(after.concat().as_bytes()).unwrap();
+        let new = vterm.snapshot();
+
+        let mut applied = new.clone();
+        applied.apply(&new.diff(&old));
+        prop_assert_eq!(applied, old);
+    }
+}
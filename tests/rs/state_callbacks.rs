@@ -19,11 +19,111 @@ fn state_can_generate_put_glyph_events() {
 
     assert!(event.is_some());
     let event = event.unwrap();
-    assert_eq!(event.glyph_info.chars[0], b'a');
+    assert_eq!(event.glyph_info.chars[0], 'a');
     assert_eq!(event.pos.x, 0);
     assert_eq!(event.pos.y, 0);
 }
 
+#[test]
+fn state_put_glyph_events_carry_combining_characters() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    })
+    .unwrap();
+    vterm.set_utf8(true);
+    vterm.state_receive_events(&StateCallbacksConfig::all());
+    vterm.write("a\u{308}".as_bytes()).unwrap();
+
+    let rx = vterm.state_event_rx.take().unwrap();
+    let events: Vec<PutGlyphEvent> = rx
+        .try_iter()
+        .filter_map(|event| match event {
+            StateEvent::PutGlyph(e) => Some(e),
+            _ => None,
+        })
+        .collect();
+
+    // The combining character redraws the glyph it joins
+    assert_eq!(events.last().unwrap().glyph_info.text(), "a\u{308}");
+    assert_eq!(events.last().unwrap().pos, Pos::new(0, 0));
+}
+
+#[test]
+fn state_can_generate_put_glyph_events_for_protected_cells() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 5,
+    })
+    .unwrap();
+    vterm.state_receive_events(&StateCallbacksConfig::all());
+    let rx = vterm.state_event_rx.take().unwrap();
+
+    // DECSCA on then off, from vendor/libvterm/t/45screen_protect.test
+    vterm.write(b"A\x1b[1\"qB\x1b[\"qC").unwrap();
+
+    let events: Vec<PutGlyphEvent> = (0..3)
+        .map(|_| try_recv_put_glyph_event(&rx).unwrap())
+        .collect();
+    assert_eq!(
+        events
+            .iter()
+            .map(|e| e.glyph_info.protected_cell)
+            .collect::<Vec<bool>>(),
+        vec![false, true, false]
+    );
+}
+
+#[test]
+fn state_can_generate_put_glyph_events_for_double_width_lines() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 5,
+    })
+    .unwrap();
+    vterm.state_receive_events(&StateCallbacksConfig::all());
+    let rx = vterm.state_event_rx.take().unwrap();
+
+    // from vendor/libvterm/t/28state_dbl_wh.test
+    vterm.write(b"\x1b#6H").unwrap();
+
+    let event = try_recv_put_glyph_event(&rx).unwrap();
+    assert_eq!(event.glyph_info.dwl, true);
+    assert_eq!(event.glyph_info.dhl, 0);
+    assert_eq!(event.glyph_info.protected_cell, false);
+    assert_eq!(event.glyph_info.width, 1);
+
+    vterm.write(b"\r\n\x1b#5H").unwrap();
+
+    let event = try_recv_put_glyph_event(&rx).unwrap();
+    assert_eq!(event.glyph_info.dwl, false);
+    assert_eq!(event.glyph_info.dhl, 0);
+}
+
+#[test]
+fn state_can_generate_put_glyph_events_for_double_height_lines() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 5,
+    })
+    .unwrap();
+    vterm.state_receive_events(&StateCallbacksConfig::all());
+    let rx = vterm.state_event_rx.take().unwrap();
+
+    // from vendor/libvterm/t/28state_dbl_wh.test
+    vterm.write(b"\x1b#3H").unwrap();
+
+    let event = try_recv_put_glyph_event(&rx).unwrap();
+    assert_eq!(event.glyph_info.dwl, true);
+    assert_eq!(event.glyph_info.dhl, 1);
+
+    vterm.write(b"\r\n\x1b#4H").unwrap();
+
+    let event = try_recv_put_glyph_event(&rx).unwrap();
+    assert_eq!(event.glyph_info.dwl, true);
+    assert_eq!(event.glyph_info.dhl, 2);
+}
+
 #[test]
 fn state_can_generate_move_cursor_events() {
     let mut vterm: VTerm = VTerm::new(&Size {
@@ -832,6 +932,86 @@ fn state_can_generate_resize_events() {
     assert_eq!(event.size, Size::new(2, 3));
 }
 
+#[test]
+fn state_can_generate_line_info_events() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 5,
+    })
+    .unwrap();
+    vterm.state_receive_events(&StateCallbacksConfig::all());
+    let rx = vterm.state_event_rx.take().unwrap();
+
+    // DECDHL top half
+    vterm.write(b"\x1b#3").unwrap();
+
+    let event = try_recv_line_info_event(&rx).unwrap();
+    assert_eq!(event.row, 0);
+    assert_eq!(
+        event.new,
+        LineInfo {
+            double_width: true,
+            double_height: DoubleHeight::Top,
+            continuation: false,
+        }
+    );
+    assert_eq!(event.old, LineInfo::default());
+
+    // DECSWL
+    vterm.write(b"\x1b#5").unwrap();
+
+    let event = try_recv_line_info_event(&rx).unwrap();
+    assert_eq!(event.row, 0);
+    assert_eq!(event.new, LineInfo::default());
+    assert_eq!(
+        event.old,
+        LineInfo {
+            double_width: true,
+            double_height: DoubleHeight::Top,
+            continuation: false,
+        }
+    );
+}
+
+#[test]
+fn state_can_generate_pen_color_events() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 5,
+    })
+    .unwrap();
+    vterm.state_receive_events(&StateCallbacksConfig::all());
+    let rx = vterm.state_event_rx.take().unwrap();
+
+    vterm.write(b"\x1b[31;48;2;1;2;3m").unwrap();
+
+    let event = try_recv_pen_foreground_event(&rx).unwrap();
+    assert_eq!(event.color, Color::Indexed(1));
+    assert_eq!(event.palette, 1);
+    let event = try_recv_pen_background_event(&rx).unwrap();
+    assert_eq!(
+        event.color,
+        Color::Rgb(ColorRGB {
+            red: 1,
+            green: 2,
+            blue: 3,
+        })
+    );
+
+    // Palette entry 7 and the default foreground have the same rgb here, but not the same color
+    vterm.state_set_default_colors(
+        &vterm.state_get_rgb_color_from_palette(7),
+        &ColorRGB::default(),
+    );
+    vterm.write(b"\x1b[39m\x1b[38;5;7m").unwrap();
+
+    let event = try_recv_pen_foreground_event(&rx).unwrap();
+    assert_eq!(event.color, Color::Default);
+    let event = try_recv_pen_foreground_event(&rx).unwrap();
+    assert_eq!(event.color, Color::Indexed(7));
+    assert_eq!(event.palette, 7);
+}
+
 // Builds a function that returns a Some of the first event of the given type found on the channel
 // or None.
 macro_rules! dry {
@@ -877,7 +1057,11 @@ dry!(
     StateEvent::IconName
 );
 dry!(try_recv_init_pen_event, InitPenEvent, StateEvent::InitPen);
-//dry!(try_recv_line_info_event, LineInfoEvent, StateEvent::LineInfo);
+dry!(
+    try_recv_line_info_event,
+    LineInfoEvent,
+    StateEvent::LineInfo
+);
 dry!(try_recv_mouse_event, MouseEvent, StateEvent::Mouse);
 dry!(
     try_recv_move_cursor_event,
@@ -0,0 +1,80 @@
+#![allow(unused_variables)]
+
+use std::cell::RefCell;
+use std::io::prelude::*;
+use std::rc::Rc;
+use vterm_sys::*;
+
+struct ScrollHandler {
+    handles_scroll: bool,
+    calls: Rc<RefCell<Vec<&'static str>>>,
+}
+
+impl StateHandler for ScrollHandler {
+    fn scroll_rect(&mut self, rect: Rect, downward: isize, rightward: isize) -> bool {
+        self.calls.borrow_mut().push("scroll_rect");
+        self.handles_scroll
+    }
+
+    fn move_rect(&mut self, dest: Rect, src: Rect) {
+        self.calls.borrow_mut().push("move_rect");
+    }
+
+    fn erase(&mut self, rect: Rect, is_selective: bool) {
+        self.calls.borrow_mut().push("erase");
+    }
+}
+
+fn scroll_calls(handles_scroll: bool) -> Vec<&'static str> {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    let calls = Rc::new(RefCell::new(vec![]));
+    vterm.set_state_handler(Box::new(ScrollHandler {
+        handles_scroll: handles_scroll,
+        calls: calls.clone(),
+    }));
+
+    vterm.write(b"\n\n").unwrap();
+
+    let calls = calls.borrow().clone();
+    calls
+}
+
+#[test]
+fn state_handler_can_handle_scroll_rect() {
+    assert_eq!(scroll_calls(true), vec!["scroll_rect"]);
+}
+
+#[test]
+fn state_handler_falls_back_to_move_rect_and_erase() {
+    assert_eq!(
+        scroll_calls(false),
+        vec!["scroll_rect", "move_rect", "erase"]
+    );
+}
+
+struct GlyphCounter(Rc<RefCell<usize>>);
+
+impl StateHandler for GlyphCounter {
+    fn put_glyph(&mut self, glyph_info: GlyphInfo, pos: Pos) {
+        *self.0.borrow_mut() += 1;
+    }
+}
+
+#[test]
+fn state_handler_receives_put_glyph() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    let count = Rc::new(RefCell::new(0));
+    vterm.set_state_handler(Box::new(GlyphCounter(count.clone())));
+
+    vterm.write(b"abc").unwrap();
+
+    assert_eq!(*count.borrow(), 3);
+}
@@ -0,0 +1,125 @@
+use std::cell::RefCell;
+use std::io::prelude::*;
+use std::rc::Rc;
+use vterm_sys::*;
+
+fn new_vterm() -> VTerm {
+    VTerm::new(&Size {
+        height: 2,
+        width: 10,
+    })
+    .unwrap()
+}
+
+fn read_output(vterm: &mut VTerm) -> Vec<u8> {
+    let mut output = vec![];
+    ::std::io::copy(vterm, &mut output).unwrap();
+    output
+}
+
+#[test]
+fn unrecognised_handler_receives_unknown_osc_but_not_known_ones() {
+    let mut vterm = new_vterm();
+    let sequences = Rc::new(RefCell::new(vec![]));
+    let seen = sequences.clone();
+    vterm.set_unrecognised_handler(Box::new(move |sequence: &Sequence, _: &mut Vec<u8>| {
+        seen.borrow_mut().push(sequence.clone());
+        true
+    }));
+
+    vterm
+        .write(b"\x1b]2;title\x07\x1b]7;file://host/tmp\x1b\\ab")
+        .unwrap();
+
+    assert_eq!(
+        *sequences.borrow(),
+        vec![Sequence::Osc {
+            command: Some(7),
+            data: b"file://host/tmp".to_vec(),
+        }]
+    );
+    assert_eq!(
+        vterm.screen_get_text_lossy(&Rect::new(Pos::new(0, 0), Size::new(2, 1))),
+        "ab"
+    );
+}
+
+#[test]
+fn unrecognised_handler_receives_unknown_csi() {
+    let mut vterm = new_vterm();
+    let sequences = Rc::new(RefCell::new(vec![]));
+    let seen = sequences.clone();
+    vterm.set_unrecognised_handler(Box::new(move |sequence: &Sequence, _: &mut Vec<u8>| {
+        seen.borrow_mut().push(sequence.clone());
+        false
+    }));
+
+    vterm.write(b"\x1b[>4;2m\x1b[2J").unwrap();
+
+    assert_eq!(
+        *sequences.borrow(),
+        vec![Sequence::Csi {
+            leader: b">".to_vec(),
+            args: vec![
+                CsiArg {
+                    value: Some(4),
+                    has_more: false,
+                },
+                CsiArg {
+                    value: Some(2),
+                    has_more: false,
+                },
+            ],
+            intermed: vec![],
+            command: b'm',
+        }]
+    );
+}
+
+struct TermcapAnswerer;
+
+impl UnrecognisedHandler for TermcapAnswerer {
+    fn handle(&mut self, sequence: &Sequence, response: &mut Vec<u8>) -> bool {
+        match *sequence {
+            Sequence::Dcs {
+                ref intermed,
+                command: b'q',
+                ref data,
+                ..
+            } if intermed == b"+" && data == b"544e" => {
+                response.extend_from_slice(b"\x1bP1+r544e=78\x1b\\");
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[test]
+fn unrecognised_handler_can_answer_through_the_output_buffer() {
+    let mut vterm = new_vterm();
+    vterm.set_unrecognised_handler(Box::new(TermcapAnswerer));
+
+    vterm.write(b"\x1bP+q544e\x1b\\").unwrap();
+    assert_eq!(read_output(&mut vterm), b"\x1bP1+r544e=78\x1b\\".to_vec());
+
+    // libvterm still answers the DCS queries it knows
+    vterm.write(b"\x1bP$qr\x1b\\").unwrap();
+    assert_eq!(read_output(&mut vterm), b"\x1bP1$r1;2r\x1b\\".to_vec());
+}
+
+#[test]
+fn unrecognised_handler_can_be_cleared() {
+    let mut vterm = new_vterm();
+    let count = Rc::new(RefCell::new(0));
+    let seen = count.clone();
+    vterm.set_unrecognised_handler(Box::new(move |_: &Sequence, _: &mut Vec<u8>| {
+        *seen.borrow_mut() += 1;
+        true
+    }));
+    vterm.write(b"\x1b]7;a\x07").unwrap();
+    vterm.clear_unrecognised_handler();
+    vterm.write(b"\x1b]7;b\x07").unwrap();
+
+    assert_eq!(*count.borrow(), 1);
+}
@@ -0,0 +1,56 @@
+use std::io::prelude::*;
+use vterm_sys::*;
+
+// Returning from a function, moving into a Vec and then into a Box all change the VTerm's
+// address, which used to leave libvterm with a dangling callback pointer.
+fn move_around(vterm: VTerm) -> Box<VTerm> {
+    let mut vterms: Vec<VTerm> = vec![];
+    vterms.push(
+        VTerm::new(&Size {
+            height: 2,
+            width: 2,
+        })
+        .unwrap(),
+    );
+    vterms.push(vterm);
+    Box::new(vterms.remove(1))
+}
+
+#[test]
+fn vterm_can_be_moved_after_receiving_screen_events() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+
+    let mut vterm = move_around(vterm);
+    vterm.write(b"a\x07").unwrap();
+    vterm.flush().unwrap();
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    assert!(rx.try_iter().any(|e| match e {
+        ScreenEvent::Bell => true,
+        _ => false,
+    }));
+}
+
+#[test]
+fn vterm_can_be_moved_after_receiving_state_events() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    })
+    .unwrap();
+    vterm.state_receive_events(&StateCallbacksConfig::all());
+
+    let mut vterm = move_around(vterm);
+    vterm.write(b"a").unwrap();
+
+    let rx = vterm.state_event_rx.take().unwrap();
+    assert!(rx.try_iter().any(|e| match e {
+        StateEvent::PutGlyph(_) => true,
+        _ => false,
+    }));
+}